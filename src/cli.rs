@@ -1,17 +1,34 @@
-use crate::core::Babel;
-use crate::core::language::{Language, Replace, SoundChange};
-use crate::core::orth;
+use crate::core::{Babel, BabelError};
+use crate::core::class::{Category, Cell, Class};
+use crate::core::interchange::{self, Format};
+use crate::core::inventory::{Chart, Markup};
+use crate::core::language::{Language, Meta, Orthography, Replace, SoundChange, Stage};
+use crate::core::generator::Template;
+use crate::core::orth::{self, Escape};
+use crate::core::orth::sampa::{self, Notation};
+use crate::core::phonotactics::Phonotactics;
 use crate::core::pos::PoS;
-use crate::core::word::{Word, Coordinate};
+use crate::core::script::Glyph;
+use crate::core::word::{Word, Coordinate, Gloss, Sense, Shift};
+use indexmap::IndexMap;
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::fs;
 use std::io;
+use std::path::PathBuf;
+
+// Regex characters no notation uses for a sound, so they keep their meaning
+// when one is on; the rest are typed after `;`. `$` is all a replacement needs.
+const PATTERN_META: &str = "$()[]";
+const REPL_META: &str = "$";
 
 #[derive(Debug)]
 pub enum CliError {
     InvalidInput,
     LanguageInvalid,
     Modified,
+    NoMnemonic,
     NullFile,
     UnknownCommand,
 }
@@ -22,6 +39,7 @@ impl Display for CliError {
             CliError::InvalidInput => write!(f, "Invalid input!"),
             CliError::LanguageInvalid => write!(f, "You should create a language first."),
             CliError::Modified => write!(f, "You should save first."),
+            CliError::NoMnemonic => write!(f, "No mnemonic is spelt that way."),
             CliError::NullFile => write!(f, "You should specify a filename."),
             CliError::UnknownCommand => write!(f, "Unknown command."),
         }
@@ -36,6 +54,7 @@ pub struct Cli {
     cur_lang: Option<usize>,
     modified: bool,
     last_command: String,
+    notation: Option<Notation>,
 }
 
 impl Cli {
@@ -46,6 +65,7 @@ impl Cli {
             cur_lang: None,
             modified: false,
             last_command: String::new(),
+            notation: None,
         }
     }
 
@@ -67,25 +87,65 @@ impl Cli {
         eprintln!("{}", line);
     }
 
-    fn fetch(prompt: &str) -> io::Result<String> {
+    fn read(prompt: &str) -> io::Result<String> {
         Cli::prompt(prompt);
         let mut buf = String::new();
         io::stdin().read_line(&mut buf)?;
         Ok(buf.trim().to_string())
     }
 
-    fn fetch_int(prompt: &str) -> io::Result<String> {
+    fn fetch(&self, prompt: &str) -> io::Result<String> {
+        Ok(self.babel.norm().apply(&Cli::read(prompt)?))
+    }
+
+    fn fetch_int(&self, prompt: &str) -> io::Result<String> {
+        self.fetch_int_with(prompt, "")
+    }
+
+    fn fetch_int_with(&self, prompt: &str, protect: &str) -> io::Result<String> {
+        let int = self.read_int(prompt, |x, notation| sampa::from_ascii(x, notation, protect))?;
+        Ok(self.babel.norm().apply(&int))
+    }
+
+    // Patterns are kept as typed; they are normalized when compiled.
+    fn fetch_pattern(&self, prompt: &str, extra: &str) -> io::Result<String> {
+        let protect = self.protected(&format!("{}{}", PATTERN_META, extra));
+        self.read_int(prompt, |x, notation| sampa::from_ascii_regex(x, notation, &protect))
+    }
+
+    fn read_int(&self, prompt: &str, convert: impl Fn(&str, Notation) -> String) -> io::Result<String> {
         Cli::prompt(prompt);
         let mut buf = String::new();
         io::stdin().read_line(&mut buf)?;
-        let int = orth::interpret(buf.trim());
+        let int = match self.notation {
+            Some(notation) => convert(buf.trim(), notation),
+            None => orth::interpret_with(buf.trim(), &self.escapes()),
+        };
         Cli::promptln(prompt, &int);
         Ok(int)
     }
 
-    fn fetch_or(prompt: &str, default: &str) -> io::Result<String> {
+    // Category names (and whatever else the field needs) must survive a
+    // notation that would otherwise read `S` as ʃ.
+    fn protected(&self, extra: &str) -> String {
+        let mut protect = String::from(extra);
+        if let Ok(lang) = self.cur_lang() {
+            protect.extend(lang.mnemonic_transform().cat().keys());
+        }
+        protect
+    }
+
+    fn escapes(&self) -> Vec<&Escape> {
+        let mut escapes = vec![self.babel.escape()];
+        if let Ok(lang) = self.cur_lang() {
+            escapes.push(lang.escape());
+        }
+        escapes
+    }
+
+    fn fetch_or(&self, prompt: &str, default: &str) -> io::Result<String> {
         Cli::promptln(prompt, &format!("(old) {}", default));
-        Cli::fetch(prompt).map(|x| {
+        self.fetch(prompt).map(|x| {
             if x.is_empty() {
                 default.to_string()
             } else {
@@ -94,9 +154,13 @@ impl Cli {
         })
     }
 
-    fn fetch_int_or(prompt: &str, default: &str) -> io::Result<String> {
-        Cli::promptln(prompt, &format!("(old) {}", default));
-        Cli::fetch_int(prompt).map(|x| {
+    fn fetch_int_or(&self, prompt: &str, default: &str) -> io::Result<String> {
+        self.fetch_int_or_with(prompt, default, "")
+    }
+
+    fn fetch_int_or_with(&self, prompt: &str, default: &str, protect: &str) -> io::Result<String> {
+        self.show_old(prompt, default);
+        self.fetch_int_with(prompt, protect).map(|x| {
             if x.is_empty() {
                 default.to_string()
             } else {
@@ -105,14 +169,37 @@ impl Cli {
         })
     }
 
+    fn fetch_pattern_or(&self, prompt: &str, default: &str, extra: &str) -> io::Result<String> {
+        self.show_old(prompt, default);
+        self.fetch_pattern(prompt, extra).map(|x| {
+            if x.is_empty() {
+                default.to_string()
+            } else {
+                x
+            }
+        })
+    }
+
+    fn show_old(&self, prompt: &str, default: &str) {
+        let expressed = match self.notation {
+            Some(notation) => sampa::to_ascii(default, notation),
+            None => orth::express_with(default, &self.escapes()),
+        };
+        if expressed == default {
+            Cli::promptln(prompt, &format!("(old) {}", default));
+        } else {
+            Cli::promptln(prompt, &format!("(old) {} = {}", default, expressed));
+        }
+    }
+
     fn fetch_idx(prompt: &str) -> Result<usize, Box<dyn Error>> {
-        let idx: usize = Cli::fetch(prompt)?.parse()?;
+        let idx: usize = Cli::read(prompt)?.parse()?;
         Ok(idx)
     }
 
     fn fetch_idx_or(prompt: &str, default: usize) -> Result<usize, Box<dyn Error>> {
         Cli::promptln(prompt, &format!("(old) {}", default));
-        let x = Cli::fetch(prompt)?;
+        let x = Cli::read(prompt)?;
         let idx: usize = if x.is_empty() {
             default
         } else {
@@ -121,93 +208,474 @@ impl Cli {
         Ok(idx)
     }
 
-    fn fetch_char_int(prompt: &str) -> Result<char, Box<dyn Error>> {
-        let name = Cli::fetch_int(prompt)?.chars().next().ok_or(CliError::InvalidInput)?;
+    fn fetch_bool_or(prompt: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+        Cli::promptln(prompt, &format!("(old) {}", if default { "y" } else { "n" }));
+        match Cli::read(prompt)?.as_str() {
+            "" => Ok(default),
+            "y" | "yes" => Ok(true),
+            "n" | "no" => Ok(false),
+            _ => Err(Box::new(CliError::InvalidInput)),
+        }
+    }
+
+    fn fetch_char_int(&self, prompt: &str) -> Result<char, Box<dyn Error>> {
+        let buf = self.fetch(prompt)?;
+        let int = orth::interpret_with(&buf, &self.escapes());
+        let name = int.chars().next().ok_or(CliError::InvalidInput)?;
         Ok(name)
     }
 
     fn build_new_lang(&self) -> io::Result<Language> {
-        let name = Cli::fetch("name")?;
+        let name = self.fetch("name")?;
         Ok(Language::new(&name))
     }
 
     // fn build_lang() -> Result<Language, Box<dyn Error>> {
-    //     let name = Cli::fetch("name")?;
+    //     let name = self.fetch("name")?;
     //     let ancestor = Cli::fetch_idx("ancestor's index")?;
     //     Ok(Language::new(&name, ancestor))
     // }
 
-    fn update_lang(old: &mut Language) -> io::Result<()> {
-        let name = Cli::fetch_or("name", old.name())?;
-        old.change_name(&name);
-        Ok(())
+    fn update_lang(&self, old: &Language) -> io::Result<String> {
+        self.fetch_or("name", old.name())
     }
 
-    fn build_pos() -> io::Result<PoS> {
-        let name = Cli::fetch("name")?;
-        let abbr = Cli::fetch("abbr")?;
+    fn build_pos(&self) -> io::Result<PoS> {
+        let name = self.fetch("name")?;
+        let abbr = self.fetch("abbr")?;
         Ok(PoS::new(&name, &abbr))
     }
 
-    fn update_pos(old: &PoS) -> io::Result<PoS> {
-        let name = Cli::fetch_or("name", old.name())?;
-        let abbr = Cli::fetch_or("abbr", old.abbr())?;
+    fn update_pos(&self, old: &PoS) -> io::Result<PoS> {
+        let name = self.fetch_or("name", old.name())?;
+        let abbr = self.fetch_or("abbr", old.abbr())?;
         Ok(PoS::new(&name, &abbr))
     }
 
-    fn build_replace() -> Result<Replace, Box<dyn Error>> {
-        let pat = Cli::fetch_int("pattern")?;
-        let repl = Cli::fetch_int("repl")?;
-        let rule = Replace::new(&pat, &repl)?;
+    fn build_meta(&self) -> io::Result<Meta> {
+        let label = self.fetch("label")?;
+        let comment = self.fetch("comment")?;
+        Ok(Meta::new(&label, &comment, true))
+    }
+
+    fn update_meta(&self, old: &Meta) -> Result<Meta, Box<dyn Error>> {
+        let label = self.fetch_or("label", old.label())?;
+        let comment = self.fetch_or("comment", old.comment())?;
+        let enabled = Cli::fetch_bool_or("enabled", old.enabled())?;
+        Ok(Meta::new(&label, &comment, enabled))
+    }
+
+    fn describe_replace(rule: &Replace) -> String {
+        if rule.env().is_empty() {
+            format!("{} -> {}", rule.pat(), rule.repl())
+        } else {
+            format!("{} -> {} / {}", rule.pat(), rule.repl(), rule.env())
+        }
+    }
+
+    fn annotate(meta: &Meta) -> String {
+        let mut note = String::new();
+        if !meta.label().is_empty() {
+            note.push_str(&format!("\t[{}]", meta.label()));
+        }
+        if !meta.enabled() {
+            note.push_str("\t(off)");
+        }
+        if !meta.comment().is_empty() {
+            note.push_str(&format!("\t# {}", meta.comment()));
+        }
+        note
+    }
+
+    // Only languages with named orthographies ask which one a rule belongs to.
+    fn fetch_orth(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        if lang.enum_orth().next().is_none() {
+            return Ok(None);
+        }
+        let key = self.fetch("orthography")?;
+        if key.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(lang.orth_idx(&key)?))
+        }
+    }
+
+    fn build_replace(&self) -> Result<Replace, Box<dyn Error>> {
+        let pat = self.fetch_pattern("pattern", "")?;
+        let repl = self.fetch_int_with("repl", &self.protected(REPL_META))?;
+        let env = self.fetch_pattern("env", "_#")?;
+        let meta = self.build_meta()?;
+        let rule = Replace::new(&pat, &repl)?.with_env(&env).with_meta(meta);
+        self.cur_lang()?.check_replace(&rule)?;
         Ok(rule)
     }
 
-    fn update_replace(old: &Replace) -> Result<Replace, Box<dyn Error>> {
-        let pat = Cli::fetch_int_or("pattern", old.pat())?;
-        let repl = Cli::fetch_int_or("repl", old.repl())?;
-        let rule = Replace::new(&pat, &repl)?;
+    fn update_replace(&self, old: &Replace) -> Result<Replace, Box<dyn Error>> {
+        let pat = self.fetch_pattern_or("pattern", old.pat(), "")?;
+        let repl = self.fetch_int_or_with("repl", old.repl(), &self.protected(REPL_META))?;
+        let env = self.fetch_pattern_or("env", old.env(), "_#")?;
+        let meta = self.update_meta(old.meta())?;
+        let rule = Replace::new(&pat, &repl)?.with_env(&env).with_meta(meta);
+        self.cur_lang()?.check_replace(&rule)?;
         Ok(rule)
     }
 
-    fn build_sound_change() -> io::Result<SoundChange> {
-        let tg = Cli::fetch_int("target")?;
-        let repl = Cli::fetch_int("repl")?;
-        let env = Cli::fetch_int("env")?;
-        Ok(SoundChange::new(&tg, &repl, &env))
+    fn build_sound_change(&self) -> Result<SoundChange, Box<dyn Error>> {
+        let tg = self.fetch_pattern("target", "")?;
+        let repl = self.fetch_int_with("repl", &self.protected(REPL_META))?;
+        let env = self.fetch_pattern("env", "_#")?;
+        let optional = Cli::fetch_bool_or("optional", false)?;
+        let meta = self.build_meta()?;
+        Ok(SoundChange::new(&tg, &repl, &env).with_optional(optional).with_meta(meta))
     }
 
-    fn update_sound_change(old: &SoundChange) -> io::Result<SoundChange> {
-        let tg = Cli::fetch_int_or("target", old.tg())?;
-        let repl = Cli::fetch_int_or("repl", old.repl())?;
-        let env = Cli::fetch_int_or("env", old.env())?;
-        Ok(SoundChange::new(&tg, &repl, &env))
+    fn update_sound_change(&self, old: &SoundChange) -> Result<SoundChange, Box<dyn Error>> {
+        let tg = self.fetch_pattern_or("target", old.tg(), "")?;
+        let repl = self.fetch_int_or_with("repl", old.repl(), &self.protected(REPL_META))?;
+        let env = self.fetch_pattern_or("env", old.env(), "_#")?;
+        let optional = Cli::fetch_bool_or("optional", old.optional())?;
+        let meta = self.update_meta(old.meta())?;
+        Ok(SoundChange::new(&tg, &repl, &env).with_optional(optional).with_meta(meta))
     }
 
-    fn build_word(&self) -> io::Result<Word> {
-        let mnemonic = Cli::fetch_int("mnemonic")?;
-        let natlang = Cli::fetch("natlang")?;
+    // Written forms are always typed with orthography escapes, whatever the
+    // notation used for IPA fields.
+    fn fetch_written(&self, prompt: &str) -> io::Result<String> {
+        let buf = self.fetch(prompt)?;
+        let int = self.babel.norm().apply(&orth::interpret_with(&buf, &self.escapes()));
+        Cli::promptln(prompt, &int);
+        Ok(int)
+    }
+
+    fn fetch_mnemonic_by_spelling(&self) -> Result<String, Box<dyn Error>> {
+        let written = self.fetch_written("written")?;
+        let mut parses = self.cur_lang()?.invert_m2w(&written)?;
+        match parses.len() {
+            0 => Err(Box::new(CliError::NoMnemonic)),
+            1 => Ok(parses.remove(0)),
+            _ => {
+                eprintln!("Ambiguous spelling:");
+                for (i, x) in parses.iter().enumerate() {
+                    eprintln!("{}. {}", i, x);
+                }
+                let idx = Cli::fetch_idx("choice")?;
+                if idx >= parses.len() {
+                    return Err(Box::new(CliError::InvalidInput));
+                }
+                Ok(parses.remove(idx))
+            }
+        }
+    }
+
+    fn parse_code(code: &str) -> Result<Option<char>, Box<dyn Error>> {
+        let code = code.trim_start_matches("U+").trim_start_matches("u+");
+        if code.is_empty() {
+            return Ok(None);
+        }
+        let code = u32::from_str_radix(code, 16)?;
+        Ok(Some(char::from_u32(code).ok_or(CliError::InvalidInput)?))
+    }
+
+    fn build_tpl(&self) -> Result<Template, Box<dyn Error>> {
+        let pattern = self.fetch("pattern")?;
+        let weight: u32 = self.fetch_or("weight", "1")?.parse()?;
+        Ok(Template::new(&pattern, weight))
+    }
+
+    // Parts of speech go in by abbreviation, separated by spaces.
+    fn fetch_apply(&self, prompt: &str, default: &[usize]) -> Result<Vec<usize>, Box<dyn Error>> {
+        let old: Vec<&str> = default.iter().filter_map(|&i| self.babel.pos_at(i).ok()).map(|x| x.abbr()).collect();
+        let abbrs = if old.is_empty() { self.fetch(prompt)? } else { self.fetch_or(prompt, &old.join(" "))? };
+        abbrs.split_whitespace().map(|x| {
+            self.babel.abbr_to_idx(x).ok_or_else(|| BabelError::UnknownLabel(x.to_owned()).into())
+        }).collect()
+    }
+
+    fn show_feature(feature: &IndexMap<String, String>) -> String {
+        feature.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" ")
+    }
+
+    // Features go in as `category=value`, separated by spaces.
+    fn parse_feature(text: &str) -> Result<IndexMap<String, String>, CliError> {
+        text.split_whitespace().map(|x| match x.split_once('=') {
+            Some((k, v)) => Ok((k.to_owned(), v.to_owned())),
+            None => Err(CliError::InvalidInput),
+        }).collect()
+    }
+
+    // One prompt per category the part of speech is marked for; anything
+    // but an allowed value or nothing is asked again.
+    fn fetch_word_feature(&self, pos: usize, old: &IndexMap<String, String>) -> io::Result<IndexMap<String, String>> {
+        let mut feature = IndexMap::new();
+        for category in self.babel.category_of(pos) {
+            let prompt = format!("{} ({})", category.name(), category.value().join("/"));
+            let old = old.get(category.name()).map(|x| x.as_str()).unwrap_or("");
+            let value = loop {
+                let value = if old.is_empty() { self.fetch(&prompt)? } else { self.fetch_or(&prompt, old)? };
+                if value.is_empty() || category.value().contains(&value) {
+                    break value;
+                }
+            };
+            if !value.is_empty() {
+                feature.insert(category.name().to_owned(), value);
+            }
+        }
+        Ok(feature)
+    }
+
+    fn build_category(&self) -> Result<Category, Box<dyn Error>> {
+        let name = self.fetch("name")?;
+        let apply = self.fetch_apply("pos", &[])?;
+        let value = self.fetch("values")?;
+        Ok(Category::new(&name, apply, value.split_whitespace().map(String::from).collect()))
+    }
+
+    fn update_category(&self, old: &Category) -> Result<Category, Box<dyn Error>> {
+        let name = self.fetch_or("name", old.name())?;
+        let apply = self.fetch_apply("pos", old.apply())?;
+        let value = self.fetch_or("values", &old.value().join(" "))?;
+        Ok(Category::new(&name, apply, value.split_whitespace().map(String::from).collect()))
+    }
+
+    // Cells are read until an empty name.
+    fn fetch_cells(&self, cells: &mut Vec<Cell>) -> io::Result<()> {
+        loop {
+            let name = self.fetch("cell")?;
+            if name.is_empty() {
+                return Ok(());
+            }
+            let template = self.fetch("template")?;
+            cells.push(Cell::new(&name, &template).with_change(self.build_change()?));
+        }
+    }
+
+    fn check_cells(&self, cells: &[Cell]) -> Result<(), Box<dyn Error>> {
+        let sca = self.cur_lang()?.mnemonic_transform();
+        for sc in cells.iter().filter_map(|x| x.change()) {
+            sca.compile(sc)?;
+        }
+        Ok(())
+    }
+
+    // A cell's change is only asked for its repl and env once a target is given.
+    fn build_change(&self) -> io::Result<Option<SoundChange>> {
+        let tg = self.fetch_pattern("change", "")?;
+        if tg.is_empty() {
+            return Ok(None);
+        }
+        let repl = self.fetch_int_with("repl", &self.protected(REPL_META))?;
+        let env = self.fetch_pattern("env", "_#")?;
+        Ok(Some(SoundChange::new(&tg, &repl, &env)))
+    }
+
+    // "-" as the target drops the change.
+    fn update_change(&self, old: Option<&SoundChange>) -> io::Result<Option<SoundChange>> {
+        let old = match old {
+            Some(old) => old,
+            None => return self.build_change(),
+        };
+        let tg = self.fetch_pattern_or("change", old.tg(), "")?;
+        if tg == "-" {
+            return Ok(None);
+        }
+        let repl = self.fetch_int_or_with("repl", old.repl(), &self.protected(REPL_META))?;
+        let env = self.fetch_pattern_or("env", old.env(), "_#")?;
+        Ok(Some(SoundChange::new(&tg, &repl, &env)))
+    }
+
+    fn build_class(&self) -> Result<Class, Box<dyn Error>> {
+        let name = self.fetch("name")?;
+        let apply = self.fetch_apply("pos", &[])?;
+        let feature = Cli::parse_feature(&self.fetch("features")?)?;
+        self.babel.check_feature(&feature, Some(&apply))?;
+        let mut cells = Vec::new();
+        self.fetch_cells(&mut cells)?;
+        self.check_cells(&cells)?;
+        Ok(Class::new(&name, apply, cells).with_feature(feature))
+    }
+
+    // Existing cells keep their place; "-" as a name drops one.
+    fn update_class(&self, old: &Class) -> Result<Class, Box<dyn Error>> {
+        let name = self.fetch_or("name", old.name())?;
+        let apply = self.fetch_apply("pos", old.apply())?;
+        let feature = Cli::parse_feature(&self.fetch_or("features", &Cli::show_feature(old.feature()))?)?;
+        self.babel.check_feature(&feature, Some(&apply))?;
+        let mut cells = Vec::new();
+        for cell in old.cell() {
+            let name = self.fetch_or("cell", cell.name())?;
+            if name == "-" {
+                continue;
+            }
+            let template = self.fetch_or("template", cell.template())?;
+            let change = self.update_change(cell.change())?;
+            cells.push(Cell::new(&name, &template).with_change(change));
+        }
+        self.fetch_cells(&mut cells)?;
+        self.check_cells(&cells)?;
+        Ok(Class::new(&name, apply, cells).with_feature(feature))
+    }
+
+    fn update_tpl(&self, old: &Template) -> Result<Template, Box<dyn Error>> {
+        let pattern = self.fetch_or("pattern", old.pattern())?;
+        let weight: u32 = self.fetch_or("weight", &old.weight().to_string())?.parse()?;
+        Ok(Template::new(&pattern, weight))
+    }
+
+    fn build_glyph(&self) -> Result<Glyph, Box<dyn Error>> {
+        let unit = self.fetch_written("unit")?;
+        let name = self.fetch("name")?;
+        let code = Cli::parse_code(&self.fetch("code")?)?;
+        let path = self.fetch("path")?;
+        let advance: f64 = self.fetch_or("advance", "1000")?.parse()?;
+        Ok(Glyph::new(&unit, &name).with_code(code).with_path(&path, advance))
+    }
+
+    fn update_glyph(&self, old: &Glyph) -> Result<Glyph, Box<dyn Error>> {
+        Cli::promptln("unit", &format!("(old) {}", old.unit()));
+        let unit = self.fetch_written("unit")?;
+        let unit = if unit.is_empty() { old.unit().to_owned() } else { unit };
+        let name = self.fetch_or("name", old.name())?;
+        let old_code = old.code().map(|x| format!("{:04X}", x as u32)).unwrap_or_default();
+        let code = Cli::parse_code(&self.fetch_or("code", &old_code)?)?;
+        let path = self.fetch_or("path", old.path())?;
+        let advance: f64 = self.fetch_or("advance", &old.advance().to_string())?.parse()?;
+        Ok(Glyph::new(&unit, &name).with_code(code).with_path(&path, advance))
+    }
+
+    fn build_word(&self) -> Result<Word, Box<dyn Error>> {
+        let mut mnemonic = self.fetch_int("mnemonic")?;
+        if mnemonic.is_empty() {
+            mnemonic = self.fetch_mnemonic_by_spelling()?;
+            Cli::promptln("mnemonic", &mnemonic);
+        }
+        self.build_word_from(&mnemonic)
+    }
+
+    fn build_word_from(&self, mnemonic: &str) -> Result<Word, Box<dyn Error>> {
         let pos = loop {
-            let abbr = Cli::fetch("pos")?;
+            let abbr = self.fetch("pos")?;
             if let Some(idx) = self.babel.abbr_to_idx(&abbr) {
                 break idx;
             }
         };
-        let info = Cli::fetch("info")?;
-        Ok(Word::shell(&mnemonic, &natlang, pos, &info))
+        let feature = self.fetch_word_feature(pos, &IndexMap::new())?;
+        let sense = self.build_sense()?;
+        let mut word = Word::shell(mnemonic, pos).with_sense(vec![sense]);
+        for (category, value) in feature {
+            word.set_feature(&category, &value);
+        }
+        Ok(word)
+    }
+
+    // Without any gloss language set, glosses go in unmarked.
+    fn build_gloss(&self) -> io::Result<Gloss> {
+        let mut gloss = Gloss::default();
+        if self.babel.glossed_lang().is_empty() {
+            gloss.set("", &self.fetch("gloss")?);
+        }
+        for code in self.babel.glossed_lang() {
+            gloss.set(code, &self.fetch(&format!("gloss ({})", code))?);
+        }
+        Ok(gloss)
+    }
+
+    fn update_gloss(&self, old: &Gloss) -> io::Result<Gloss> {
+        let mut codes: Vec<&str> = self.babel.glossed_lang().iter().map(|x| x.as_str()).collect();
+        for (code, _) in old.iter() {
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+        if codes.is_empty() {
+            codes.push("");
+        }
+        let mut gloss = Gloss::default();
+        for code in codes {
+            let prompt = if code.is_empty() { String::from("gloss") } else { format!("gloss ({})", code) };
+            gloss.set(code, &self.fetch_or(&prompt, old.text(code).unwrap_or(""))?);
+        }
+        Ok(gloss)
+    }
+
+    fn build_sense(&self) -> Result<Sense, Box<dyn Error>> {
+        let gloss = self.build_gloss()?;
+        let definition = self.fetch("definition")?;
+        let note = self.fetch("usage note")?;
+        let register = self.fetch("register")?;
+        let example = self.fetch("example")?;
+        Ok(Sense::new(gloss)
+            .with_definition(&definition)
+            .with_note(&note)
+            .with_register(&register)
+            .with_example(&example))
+    }
+
+    fn update_sense(&self, old: &Sense) -> Result<Sense, Box<dyn Error>> {
+        let gloss = self.update_gloss(old.gloss())?;
+        let definition = self.fetch_or("definition", old.definition())?;
+        let note = self.fetch_or("usage note", old.note())?;
+        let register = self.fetch_or("register", old.register())?;
+        let example = self.fetch_or("example", old.example())?;
+        let shift: Shift = self.fetch_or("shift", &old.shift().to_string())?.parse()?;
+        Ok(Sense::new(gloss)
+            .with_definition(&definition)
+            .with_note(&note)
+            .with_register(&register)
+            .with_example(&example)
+            .with_shift(shift))
+    }
+
+    fn update_tactics(&self, old: &Phonotactics) -> Result<Phonotactics, Box<dyn Error>> {
+        let list = |prompt: &str, old: &[String]| -> io::Result<Vec<String>> {
+            let x = self.fetch_int_or(prompt, &old.join(" "))?;
+            Ok(x.split_whitespace().map(String::from).collect())
+        };
+        let vowel = self.fetch_int_or("vowel category", &old.vowel().to_string())?;
+        let vowel = vowel.chars().next().ok_or(CliError::InvalidInput)?;
+        let shape = self.fetch_int_or("shape", old.shape())?;
+        let tactics = Phonotactics::new(vowel, &shape)
+            .with_onset(list("onsets", old.onset())?)
+            .with_coda(list("codas", old.coda())?)
+            .with_cluster(list("clusters", old.cluster())?)
+            .with_forbid(list("forbidden", old.forbid())?)
+            .with_strict(Cli::fetch_bool_or("strict", old.strict())?);
+        self.cur_lang()?.check_tactics(&tactics)?;
+        Ok(tactics)
+    }
+
+    // Flags what a mnemonic breaks, and whether that's enough to refuse it.
+    fn validate(&self, mnemonic: &str) -> Result<bool, Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let violations = lang.validate(mnemonic)?;
+        for violation in violations.iter() {
+            println!("{}: {}", mnemonic, violation);
+        }
+        Ok(violations.is_empty() || !lang.phonotactics().strict())
     }
 
     fn update_word(&self, old: &Word) -> Result<Word, Box<dyn Error>> {
-        let mnemonic = Cli::fetch_int_or("mnemonic", old.mnemonic())?;
-        let natlang = Cli::fetch_or("natlang", old.natlang())?;
+        let mnemonic = self.fetch_int_or("mnemonic", old.mnemonic())?;
         let old_pos = self.babel.pos_at(old.pos())?.abbr();
         let pos = loop {
-            let abbr = Cli::fetch_or("pos", old_pos)?;
+            let abbr = self.fetch_or("pos", old_pos)?;
             if let Some(idx) = self.babel.abbr_to_idx(&abbr) {
                 break idx;
             }
         };
-        let info = Cli::fetch_or("info", old.info())?;
-        Ok(Word::shell(&mnemonic, &natlang, pos, &info))
+        let feature = self.fetch_word_feature(pos, old.feature())?;
+        let mut word = Word::shell(&mnemonic, pos).with_sense(old.sense().clone());
+        word.set_class(old.class());
+        for (category, value) in feature {
+            word.set_feature(&category, &value);
+        }
+        for (cell, form) in old.inflection() {
+            word.set_inflection(cell, form);
+        }
+        for (cell, forms) in old.derived() {
+            word.set_derived(cell, forms.clone());
+        }
+        Ok(word)
     }
 
     fn build_ancestors(&self) -> Result<Vec<Coordinate>, Box<dyn Error>> {
@@ -254,6 +722,23 @@ impl Cli {
         }
     }
 
+    fn execute_add_esc(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let key = self.fetch("key")?;
+        let value = self.fetch_int("value")?;
+        self.cur_lang_mut()?.add_escape(&key, &value);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_gesc(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("key")?;
+        let value = self.fetch_int("value")?;
+        self.babel.add_escape(&key, &value);
+        self.modify();
+        Ok(())
+    }
+
     fn execute_add_lang(&mut self) -> io::Result<()> {
         let item = self.build_new_lang()?;
         self.babel.add_lang(item);
@@ -263,56 +748,120 @@ impl Cli {
     }
 
     fn execute_add_m2u(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let item = Cli::build_replace()?;
-        lang.add_m2u(item);
+        self.check_lang()?;
+        let item = self.build_replace()?;
+        self.cur_lang_mut()?.add_m2u(item)?;
         self.modify();
         Ok(())
     }
 
     fn execute_add_m2w(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let item = Cli::build_replace()?;
-        lang.add_m2w(item);
+        let orth = self.fetch_orth()?;
+        let item = self.build_replace()?;
+        self.cur_lang_mut()?.add_m2w(orth, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_glyph(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let item = self.build_glyph()?;
+        self.cur_lang_mut()?.add_glyph(item);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_tpl(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let item = self.build_tpl()?;
+        self.cur_lang_mut()?.add_tpl(item);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_class(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let item = self.build_class()?;
+        self.cur_lang_mut()?.add_class(item);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_orth(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let name = self.fetch("name")?;
+        self.cur_lang_mut()?.add_orth(Orthography::new(&name));
         self.modify();
         Ok(())
     }
 
     fn execute_add_cat(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let name = Cli::fetch_char_int("name")?;
-        let content = Cli::fetch_int("content")?;
-        lang.add_cat(name, &content);
+        self.check_lang()?;
+        let name = self.fetch_char_int("name")?;
+        let content = self.fetch_int_with("content", &self.protected("-&"))?;
+        self.cur_lang_mut()?.add_cat(name, &content)?;
         self.modify();
         Ok(())
     }
 
     fn execute_add_mnt(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_lang()?;
+        let sc = self.build_sound_change()?;
+        self.cur_lang_mut()?.add_mnt(sc)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_stg(&mut self) -> Result<(), Box<dyn Error>> {
+        let name = self.fetch("name")?;
         let lang = self.cur_lang_mut()?;
-        let sc = Cli::build_sound_change()?;
-        lang.add_mnt(sc)?;
+        let end = Cli::fetch_idx_or("end", lang.mnemonic_transform().sc().len())?;
+        lang.add_stage(Stage::new(&name, end))?;
         self.modify();
         Ok(())
     }
 
     fn execute_add_pos(&mut self) -> io::Result<()> {
-        let item = Cli::build_pos()?;
+        let item = self.build_pos()?;
         self.babel.add_pos(item);
         self.modify();
         Ok(())
     }
 
+    fn execute_add_feat(&mut self) -> Result<(), Box<dyn Error>> {
+        let item = self.build_category()?;
+        self.babel.add_category(item);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_add_sense(&mut self) -> Result<(), Box<dyn Error>> {
+        let idx = Cli::fetch_idx("index")?;
+        self.cur_lang()?.word_at(idx)?;
+        let sense = self.build_sense()?;
+        self.cur_lang_mut()?.word_at_mut(idx)?.add_sense(sense);
+        self.modify();
+        Ok(())
+    }
+
     fn execute_add_word(&mut self) -> Result<(), Box<dyn Error>> {
         let word = self.build_word()?;
-        let lang = self.cur_lang_mut()?;
-        lang.add_word(word);
+        self.accept_word(word)
+    }
+
+    fn accept_word(&mut self, word: Word) -> Result<(), Box<dyn Error>> {
+        if !self.validate(word.mnemonic())? {
+            return Err(Box::new(BabelError::AdditionRejected));
+        }
+        self.cur_lang_mut()?.add_word(word)?;
+        self.modify();
         Ok(())
     }
 
     fn execute_alt_lang(&mut self) -> Result<(), Box<dyn Error>> {
         let idx = Cli::fetch_idx("index")?;
-        let old = self.babel.lang_at_mut(idx)?;
-        Cli::update_lang(old)?;
+        let name = self.update_lang(self.babel.lang_at(idx)?)?;
+        self.babel.lang_at_mut(idx)?.change_name(&name);
         self.modify();
         self.cur_lang = Some(idx);
         Ok(())
@@ -320,39 +869,120 @@ impl Cli {
 
     fn execute_alt_m2u(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
-        let idx = Cli::fetch_idx("index")?;
+        let idx = lang.m2u_idx(&self.fetch("index")?)?;
         let old = lang.m2u_at(idx)?;
-        let item = Cli::update_replace(old)?;
+        let item = self.update_replace(old)?;
         self.cur_lang_mut()?.alt_m2u(idx, item)?;
         self.modify();
         Ok(())
     }
 
     fn execute_alt_m2w(&mut self) -> Result<(), Box<dyn Error>> {
+        let orth = self.fetch_orth()?;
+        let lang = self.cur_lang()?;
+        let idx = lang.m2w_idx(orth, &self.fetch("index")?)?;
+        let old = lang.m2w_at(orth, idx)?;
+        let item = self.update_replace(old)?;
+        self.cur_lang_mut()?.alt_m2w(orth, idx, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_abc(&mut self) -> Result<(), Box<dyn Error>> {
+        let old = self.cur_lang()?.alphabet().join(" ");
+        Cli::promptln("alphabet", &format!("(old) {}", old));
+        let alphabet = self.fetch_written("alphabet")?;
+        if !alphabet.is_empty() {
+            self.cur_lang_mut()?.set_alphabet(alphabet.split_whitespace().map(String::from).collect());
+            self.modify();
+        }
+        Ok(())
+    }
+
+    fn execute_alt_tac(&mut self) -> Result<(), Box<dyn Error>> {
+        let tactics = self.update_tactics(self.cur_lang()?.phonotactics())?;
+        self.cur_lang_mut()?.set_phonotactics(tactics);
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_glyph(&mut self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let idx = lang.glyph_idx(&self.fetch("index")?)?;
+        let item = self.update_glyph(lang.glyph_at(idx)?)?;
+        self.cur_lang_mut()?.alt_glyph(idx, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_tpl(&mut self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let idx = Cli::fetch_idx("index")?;
+        let item = self.update_tpl(lang.tpl_at(idx)?)?;
+        self.cur_lang_mut()?.alt_tpl(idx, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_class(&mut self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let idx = lang.class_idx(&self.fetch("index")?)?;
+        let item = self.update_class(lang.class_at(idx)?)?;
+        self.cur_lang_mut()?.alt_class(idx, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    // An empty class follows the part of speech; each cell shows its current
+    // form, and "-" makes it regular again until the next derivation.
+    fn execute_alt_infl(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
         let idx = Cli::fetch_idx("index")?;
-        let old = lang.m2w_at(idx)?;
-        let item = Cli::update_replace(old)?;
-        self.cur_lang_mut()?.alt_m2w(idx, item)?;
+        let mut word = lang.word_at(idx)?.clone();
+        let class = self.fetch_or("class", word.class())?;
+        if !class.is_empty() {
+            lang.class_idx(&class)?;
+        }
+        word.set_class(&class);
+        let mut asked = BTreeSet::new();
+        for old in lang.paradigm(&word)?.into_iter().filter(|x| asked.insert(x.cell().to_owned())) {
+            let form = self.fetch_int_or(old.cell(), old.mnemonic())?;
+            if form == "-" {
+                word.set_inflection(old.cell(), "");
+                word.set_derived(old.cell(), Vec::new());
+            } else if form != old.mnemonic() {
+                word.set_inflection(old.cell(), &form);
+            }
+        }
+        self.cur_lang_mut()?.alt_word(idx, word)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_orth(&mut self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let idx = lang.orth_idx(&self.fetch("index")?)?;
+        let name = self.fetch_or("name", lang.orth_at(idx)?.name())?;
+        self.cur_lang_mut()?.alt_orth(idx, &name)?;
         self.modify();
         Ok(())
     }
 
     fn execute_alt_cat(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
-        let name = Cli::fetch_char_int("name")?;
+        let name = self.fetch_char_int("name")?;
         let old = lang.cat_at(name)?;
-        let content = Cli::fetch_int_or("content", old)?;
-        self.cur_lang_mut()?.add_cat(name, &content);
+        let content = self.fetch_int_or_with("content", old, &self.protected("-&"))?;
+        self.cur_lang_mut()?.add_cat(name, &content)?;
         self.modify();
         Ok(())
     }
 
     fn execute_alt_mnt(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
-        let idx = Cli::fetch_idx("index")?;
+        let idx = lang.mnt_idx(&self.fetch("index")?)?;
         let old = lang.mnt_at(idx)?;
-        let sc = Cli::update_sound_change(old)?;
+        let sc = self.update_sound_change(old)?;
         self.cur_lang_mut()?.alt_mnt(idx, sc)?;
         self.modify();
         Ok(())
@@ -361,26 +991,192 @@ impl Cli {
     fn execute_alt_pos(&mut self) -> Result<(), Box<dyn Error>> {
         let idx = Cli::fetch_idx("index")?;
         let old = self.babel.pos_at(idx)?;
-        let item = Cli::update_pos(old)?;
+        let item = self.update_pos(old)?;
         self.babel.alt_pos(idx, item)?;
         self.modify();
         Ok(())
     }
 
+    fn execute_alt_feat(&mut self) -> Result<(), Box<dyn Error>> {
+        let idx = self.babel.category_idx(&self.fetch("index")?)?;
+        let item = self.update_category(self.babel.category_at(idx)?)?;
+        self.babel.alt_category(idx, item)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_alt_sense(&mut self) -> Result<(), Box<dyn Error>> {
+        let idx = Cli::fetch_idx("index")?;
+        let sense = Cli::fetch_idx("sense")?;
+        let item = self.update_sense(self.cur_lang()?.word_at(idx)?.sense_at(sense)?)?;
+        self.cur_lang_mut()?.word_at_mut(idx)?.alt_sense(sense, item)?;
+        self.modify();
+        Ok(())
+    }
+
     fn execute_alt_word(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
         let idx = Cli::fetch_idx("index")?;
         let old = lang.word_at(idx)?;
         let item = self.update_word(old)?;
+        if !self.validate(item.mnemonic())? {
+            return Err(Box::new(BabelError::AlterationRejected));
+        }
         self.cur_lang_mut()?.alt_word(idx, item)?;
         self.modify();
         Ok(())
     }
 
-    fn execute_cat_word(&self) -> Result<(), Box<dyn Error>> {
+    fn spelling(lang: &Language, word: &Word, orth: Option<usize>) -> Result<String, Box<dyn Error>> {
+        match orth {
+            Some(orth) => Ok(lang.spell(word, orth)?),
+            None => Ok(word.conlang().to_owned()),
+        }
+    }
+
+    fn execute_cat_word(&self, orth: &str) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let orth = if orth.is_empty() { None } else { Some(lang.orth_idx(orth)?) };
         let idx = Cli::fetch_idx("index")?;
-        let word = self.cur_lang()?.word_at(idx)?;
-        println!("{}", self.babel.illustrate_word(word));
+        let word = lang.word_at(idx)?;
+        let spelling = Cli::spelling(lang, word, orth)?;
+        println!("{}", self.babel.illustrate_word(word, &spelling, &lang.spell_all(word)?));
+        if lang.enum_glyph().next().is_some() {
+            println!("script:\t\t{}", lang.script(word.conlang()));
+        }
+        let paradigm = lang.paradigm(word)?;
+        if !paradigm.is_empty() {
+            println!("paradigm:\t{}", lang.class_of(word).map(|x| x.name()).unwrap_or("-"));
+            for form in paradigm {
+                let mark = if form.irregular() { "*" } else { "" };
+                println!("\t{}:\t{} [{}] ({}){}", form.cell(), form.conlang(), form.upa(), form.mnemonic(), mark);
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_cat_guide(&self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        for letter in lang.pronunciation_guide()? {
+            for sound in letter.sound() {
+                let example: Vec<_> = sound.example().iter().filter_map(|&i| lang.word_at(i).ok())
+                    .map(|x| x.conlang())
+                    .collect();
+                println!("{}\t{}\t[{}]\t{}\t{}", letter.written(), letter.mnemonic(), sound.ipa(), sound.context(), example.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    fn print_chart(chart: &Chart) {
+        println!("\t{}", chart.column().join("\t"));
+        for (r, row) in chart.row().iter().enumerate() {
+            let cells: Vec<String> = (0..chart.column().len()).map(|c| chart.cell(r, c).join(" ")).collect();
+            println!("{}\t{}", row, cells.join("\t"));
+        }
+    }
+
+    fn execute_cat_inv(&self) -> Result<(), Box<dyn Error>> {
+        let inventory = self.cur_lang()?.inventory();
+        for chart in [inventory.consonant(), inventory.vowel()] {
+            if !chart.is_empty() {
+                Cli::print_chart(chart);
+                println!();
+            }
+        }
+        if !inventory.other().is_empty() {
+            println!("other:\t\t{}", inventory.other().join(" "));
+        }
+        if !inventory.unattested().is_empty() {
+            println!("unattested:\t{}", inventory.unattested().join(" "));
+        }
+        let frequency: Vec<String> = inventory.frequency().iter().map(|(x, n)| format!("{} {}", x, n)).collect();
+        println!("frequency:\t{}", frequency.join(", "));
+        Ok(())
+    }
+
+    fn execute_cat_stg(&self) -> Result<(), Box<dyn Error>> {
+        let end = self.cur_lang()?.stage_end(&self.fetch("stage")?)?;
+        for (i, word) in self.babel.glimpse(self.check_lang()?, end)? {
+            println!("{}.\t{}\t{}", i, self.babel.summarize_word(&word), word.mnemonic());
+        }
+        Ok(())
+    }
+
+    fn execute_chk_m2w(&self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let report = lang.check_ambiguity()?;
+        if report.is_empty() {
+            println!("No ambiguity found.");
+            return Ok(());
+        }
+        for (written, words) in report.collision() {
+            let words: Vec<_> = words.iter().map(|&i| match lang.word_at(i) {
+                Ok(word) => format!("{}. {}", i, word.mnemonic()),
+                Err(_) => format!("{}. ?", i),
+            }).collect();
+            println!("collision\t{}\t{}", written, words.join(", "));
+        }
+        for (i, parses) in report.ambiguous() {
+            println!("ambiguous\t{}. {}\t{}", i, lang.word_at(*i)?.mnemonic(), parses.join(", "));
+        }
+        for i in report.unparsed() {
+            println!("unparsed\t{}. {}", i, lang.word_at(*i)?.mnemonic());
+        }
+        for i in report.unchecked() {
+            println!("unchecked\t{}. {}", i, lang.word_at(*i)?.mnemonic());
+        }
+        for i in report.truncated() {
+            println!("truncated\t{}. {}", i, lang.word_at(*i)?.mnemonic());
+        }
+        for i in report.skipped() {
+            println!("skipped\t{}. {}", i, Cli::describe_replace(lang.m2w_at(None, *i)?));
+        }
+        Ok(())
+    }
+
+    fn report_tactics(&self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        for (i, violations) in lang.validate_all()? {
+            let word = lang.word_at(i)?;
+            for violation in violations {
+                println!("{}. {}\t{}", i, word.mnemonic(), violation);
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_chk_tac(&self) -> Result<(), Box<dyn Error>> {
+        if self.cur_lang()?.validate_all()?.is_empty() {
+            println!("No violation found.");
+        }
+        self.report_tactics()
+    }
+
+    fn execute_gen(&mut self, count: &str) -> Result<(), Box<dyn Error>> {
+        let count: usize = if count.is_empty() { 10 } else { count.parse()? };
+        let lang = self.cur_lang()?;
+        let coined = lang.generate(count, &mut rand::thread_rng())?;
+        for (i, mnemonic) in coined.iter().enumerate() {
+            let word = lang.preview(mnemonic)?;
+            println!("{}.\t{}\t{}\t[{}]", i, mnemonic, word.conlang(), word.upa());
+        }
+        let mut accepted = BTreeSet::new();
+        loop {
+            let x = self.fetch("accept")?;
+            if x.is_empty() {
+                break;
+            }
+            let idx = x.parse::<usize>()?;
+            let mnemonic = coined.get(idx).ok_or(BabelError::IndexOutOfRange)?;
+            if accepted.contains(&idx) {
+                println!("{} is already accepted", mnemonic);
+                continue;
+            }
+            let word = self.build_word_from(mnemonic)?;
+            self.accept_word(word)?;
+            accepted.insert(idx);
+        }
         Ok(())
     }
 
@@ -396,7 +1192,7 @@ impl Cli {
         // println!("{:#?}", self.babel);
         let lang = self.cur_lang()?;
         let sca = lang.mnemonic_transform().compile_all()?;
-        let mut mnemonic = Cli::fetch_int("mnemonic")?;
+        let mut mnemonic = self.fetch_int("mnemonic")?;
         for sub in &sca {
             mnemonic = sub.pat().replace_all(&mnemonic, sub.repl()).into_owned();
         }
@@ -408,9 +1204,20 @@ impl Cli {
     fn execute_derive(&mut self) -> Result<(), Box<dyn Error>> {
         let lang = self.check_lang()?;
         let ancestor_idx = Cli::fetch_idx("ancestor's index")?;
-        self.babel.derive(lang, ancestor_idx)?;
+        let stage = self.fetch("stage")?;
+        let end = if stage.is_empty() {
+            None
+        } else {
+            Some(self.cur_lang()?.stage_end(&stage)?)
+        };
+        self.babel.derive(lang, ancestor_idx, end)?;
         self.modify();
-        Ok(())
+        let inherit = self.babel.lang_at(lang)?.enum_class().next().is_none()
+            && self.babel.lang_at(ancestor_idx)?.enum_class().next().is_some();
+        if inherit && Cli::fetch_bool_or("copy the ancestor's classes", false)? {
+            self.babel.copy_class(lang, ancestor_idx)?;
+        }
+        self.report_tactics()
     }
     
     fn execute_etym(&mut self) -> Result<(), Box<dyn Error>> {
@@ -424,44 +1231,117 @@ impl Cli {
         Ok(())
     }
 
+    fn execute_exp_inv(&self, markup: &str, file: &str) -> Result<(), Box<dyn Error>> {
+        let markup: Markup = markup.parse()?;
+        fs::write(file, self.cur_lang()?.export_inventory(markup))?;
+        println!("Exported to {}", file);
+        Ok(())
+    }
+
+    fn execute_exp_mnt(&self, format: &str, file: &str) -> Result<(), Box<dyn Error>> {
+        let format: Format = format.parse()?;
+        let (text, report) = interchange::export(self.cur_lang()?.mnemonic_transform(), format);
+        fs::write(file, text)?;
+        for line in report {
+            println!("{}", line);
+        }
+        println!("Exported to {}", file);
+        Ok(())
+    }
+
+    fn execute_exp_guide(&self, file: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(file, self.cur_lang()?.export_guide()?)?;
+        println!("Exported to {}", file);
+        Ok(())
+    }
+
+    fn execute_exp_svg(&self, file: &str) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let text = self.fetch_int("text")?;
+        let lines: Vec<&str> = if text.is_empty() {
+            lang.sorted_word().into_iter().map(|(_, x)| x.mnemonic()).collect()
+        } else {
+            vec![text.as_str()]
+        };
+        fs::write(file, lang.export_svg(&lines)?)?;
+        println!("Exported to {}", file);
+        Ok(())
+    }
+
+    fn execute_exp_word(&self, file: &str) -> Result<(), Box<dyn Error>> {
+        let text = self.babel.export_vocab(self.cur_lang()?)?;
+        fs::write(file, text)?;
+        println!("Exported to {}", file);
+        Ok(())
+    }
+
+    fn execute_imp_mnt(&mut self, format: &str, file: &str) -> Result<(), Box<dyn Error>> {
+        let format: Format = format.parse()?;
+        let text = fs::read_to_string(file)?;
+        let (sca, report) = interchange::import(&text, format);
+        let old = self.cur_lang()?.mnemonic_transform();
+        let existing = !(old.cat().is_empty() && old.sc().is_empty() && old.stage().is_empty());
+        if existing && !Cli::fetch_bool_or("replace existing categories, rules and stages", false)? {
+            return Ok(());
+        }
+        self.cur_lang_mut()?.set_mnemonic_transform(sca);
+        self.modify();
+        for line in report {
+            println!("{}", line);
+        }
+        Ok(())
+    }
+
+    fn execute_exp_stg(&mut self) -> Result<(), Box<dyn Error>> {
+        let lang = self.check_lang()?;
+        let stage = self.cur_lang()?.mnemonic_transform().stage_idx(&self.fetch("stage")?)?;
+        let idx = self.babel.split_lang(lang, stage)?;
+        self.modify();
+        println!("{}. {}", idx, self.babel.summarize_lang(self.babel.lang_at(idx)?));
+        Ok(())
+    }
+
     fn execute_ins_m2u(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let idx = Cli::fetch_idx("index")?;
-        let item = Cli::build_replace()?;
-        lang.ins_m2u(idx, item)?;
+        let idx = self.cur_lang()?.m2u_idx(&self.fetch("index")?)?;
+        let item = self.build_replace()?;
+        self.cur_lang_mut()?.ins_m2u(idx, item)?;
         self.modify();
         Ok(())
     }
 
     fn execute_ins_m2w(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let idx = Cli::fetch_idx("index")?;
-        let item = Cli::build_replace()?;
-        lang.ins_m2w(idx, item)?;
+        let orth = self.fetch_orth()?;
+        let idx = self.cur_lang()?.m2w_idx(orth, &self.fetch("index")?)?;
+        let item = self.build_replace()?;
+        self.cur_lang_mut()?.ins_m2w(orth, idx, item)?;
         self.modify();
         Ok(())
     }
 
     fn execute_ins_mnt(&mut self) -> Result<(), Box<dyn Error>> {
-        let lang = self.cur_lang_mut()?;
-        let idx = Cli::fetch_idx("index")?;
-        let sc = Cli::build_sound_change()?;
-        lang.ins_mnt(idx, sc)?;
+        let idx = self.cur_lang()?.mnt_idx(&self.fetch("index")?)?;
+        let sc = self.build_sound_change()?;
+        self.cur_lang_mut()?.ins_mnt(idx, sc)?;
         self.modify();
         Ok(())
     }
 
-    fn execute_int(string: &str) {
-        let interpreted = orth::interpret(string);
+    fn execute_int(&self, string: &str) {
+        let interpreted = orth::interpret_with(string, &self.escapes());
         println!("{}", interpreted);
     }
 
+    fn execute_xpr(&self, string: &str) {
+        let expressed = orth::express_with(string, &self.escapes());
+        println!("{}", expressed);
+    }
+
     fn execute_load(&mut self, file: &str) -> Result<(), Box<dyn Error>> {
         self.check_modified()?;
         let neo_babel = Babel::load(file)?;
         self.babel = neo_babel;
         self.filename = file.to_string();
-        if self.babel.lang().len() > 0 {
+        if !self.babel.lang().is_empty() {
             self.cur_lang = Some(0);
             let lang = self.cur_lang()?;
             println!("0. {}", self.babel.summarize_lang(lang));
@@ -471,54 +1351,291 @@ impl Cli {
         Ok(())
     }
 
+    fn execute_ls_esc(&self) {
+        for (key, value) in self.babel.escape() {
+            println!("*\t{}\t{}", key, value);
+        }
+        if let Ok(lang) = self.cur_lang() {
+            for (key, value) in lang.escape() {
+                println!("{}\t{}\t{}", lang.name(), key, value);
+            }
+        }
+    }
+
     fn execute_ls_lang(&self) {
         for (i, lang) in self.babel.enum_lang() {
             println!("{}. {}", i, self.babel.summarize_lang(lang));
         }
     }
 
-    fn execute_ls_m2w(&self) -> Result<(), Box<dyn Error>> {
-        for (i, rule) in self.cur_lang()?.enum_m2w() {
-            println!("{}. {} -> {}", i, rule.pat(), rule.repl());
+    fn execute_ls_m2w(&self, orth: &str) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        let orth = if orth.is_empty() { None } else { Some(lang.orth_idx(orth)?) };
+        for (i, rule) in lang.enum_m2w(orth)? {
+            println!("{}. {}{}", i, Cli::describe_replace(rule), Cli::annotate(rule.meta()));
         }
         Ok(())
     }
 
     fn execute_ls_m2u(&self) -> Result<(), Box<dyn Error>> {
         for (i, rule) in self.cur_lang()?.enum_m2u() {
-            println!("{}. {} -> {}", i, rule.pat(), rule.repl());
+            println!("{}. {}{}", i, Cli::describe_replace(rule), Cli::annotate(rule.meta()));
         }
         Ok(())
     }
 
     fn execute_ls_cat(&self) -> Result<(), Box<dyn Error>> {
-        for (i, (&name, content)) in self.cur_lang()?.enum_cat() {
-            println!("{}. {} = {}", i, name, content);
+        let lang = self.cur_lang()?;
+        let resolved = lang.mnemonic_transform().resolve()?;
+        for (i, (&name, content)) in lang.enum_cat() {
+            if resolved[&name] == *content {
+                println!("{}. {} = {}", i, name, content);
+            } else {
+                println!("{}. {} = {} = {}", i, name, content, resolved[&name]);
+            }
         }
         Ok(())
     }
 
     fn execute_ls_mnt(&self) -> Result<(), Box<dyn Error>> {
         for (i, rule) in self.cur_lang()?.enum_mnt() {
-            println!("{}.\t{:4} ->  {:4} /  {}", i, rule.tg(), rule.repl(), rule.env());
+            let arrow = if rule.optional() { "~>" } else { "->" };
+            println!("{}.\t{:4} {}  {:4} /  {}{}", i, rule.tg(), arrow, rule.repl(), rule.env(), Cli::annotate(rule.meta()));
         }
         Ok(())
     }
 
+    fn execute_ls_stg(&self) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        for (i, stage) in lang.enum_stage() {
+            let start = lang.mnemonic_transform().stage_start(i)?;
+            println!("{}. {}\t{}..{}", i, stage.name(), start, stage.end());
+        }
+        Ok(())
+    }
+
+    fn execute_ls_feat(&self) {
+        for (i, category) in self.babel.enum_category() {
+            let pos: Vec<&str> = category.apply().iter().filter_map(|&x| self.babel.pos_at(x).ok()).map(|x| x.abbr()).collect();
+            println!("{}. {}\t({})\t{}", i, category.name(), pos.join(" "), category.value().join(" "));
+        }
+    }
+
     fn execute_ls_pos(&self) {
         for (i, pos) in self.babel.enum_pos() {
             println!("{}. {}({})", i, pos.name(), pos.abbr());
         }
     }
 
-    fn execute_ls_word(&self) -> Result<(), Box<dyn Error>> {
+    fn execute_ls_abc(&self) -> Result<(), Box<dyn Error>> {
+        println!("{}", self.cur_lang()?.alphabet().join(" "));
+        Ok(())
+    }
+
+    fn execute_ls_tac(&self) -> Result<(), Box<dyn Error>> {
+        let tactics = self.cur_lang()?.phonotactics();
+        println!("vowel:\t\t{}", tactics.vowel());
+        println!("shape:\t\t{}", tactics.shape());
+        println!("onsets:\t\t{}", tactics.onset().join(" "));
+        println!("codas:\t\t{}", tactics.coda().join(" "));
+        println!("clusters:\t{}", tactics.cluster().join(" "));
+        println!("forbidden:\t{}", tactics.forbid().join(" "));
+        println!("strict:\t\t{}", if tactics.strict() { "y" } else { "n" });
+        Ok(())
+    }
+
+    fn execute_ls_glyph(&self) -> Result<(), Box<dyn Error>> {
+        for (i, glyph) in self.cur_lang()?.enum_glyph() {
+            let code = glyph.code().map(|x| format!("U+{:04X} {}", x as u32, x)).unwrap_or_default();
+            let drawn = if glyph.path().is_empty() { "" } else { "\t(path)" };
+            println!("{}. {}\t{}\t{}{}", i, glyph.unit(), glyph.name(), code, drawn);
+        }
+        Ok(())
+    }
+
+    fn execute_ls_tpl(&self) -> Result<(), Box<dyn Error>> {
+        for (i, tpl) in self.cur_lang()?.enum_tpl() {
+            println!("{}. {}\t{}", i, tpl.pattern(), tpl.weight());
+        }
+        Ok(())
+    }
+
+    fn execute_ls_class(&self) -> Result<(), Box<dyn Error>> {
+        for (i, class) in self.cur_lang()?.enum_class() {
+            let pos: Vec<&str> = class.apply().iter().filter_map(|&x| self.babel.pos_at(x).ok()).map(|x| x.abbr()).collect();
+            let cells: Vec<String> = class.cell().iter().map(|x| match x.change() {
+                Some(sc) => format!("{}: {} > {} -> {} / {}", x.name(), x.template(), sc.tg(), sc.repl(), sc.env()),
+                None => format!("{}: {}", x.name(), x.template()),
+            }).collect();
+            println!("{}. {}\t({})\t{}\t{}", i, class.name(), pos.join(" "), Cli::show_feature(class.feature()), cells.join(", "));
+        }
+        Ok(())
+    }
+
+    fn execute_ls_orth(&self) -> Result<(), Box<dyn Error>> {
+        for (i, orth) in self.cur_lang()?.enum_orth() {
+            println!("{}. {} ({} rules)", i, orth.name(), orth.rule().len());
+        }
+        Ok(())
+    }
+
+    // Arguments of the form `category=value` filter the list; any other is
+    // taken for the orthography.
+    fn execute_ls_word(&self, args: Vec<&str>) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
-        for (i, word) in lang.enum_word() {
+        let (filter, orth): (Vec<&str>, Vec<&str>) = args.into_iter().partition(|x| x.contains('='));
+        let filter = Cli::parse_feature(&filter.join(" "))?;
+        self.babel.check_feature(&filter, None)?;
+        let orth = match orth.first() {
+            Some(orth) => Some(lang.orth_idx(orth)?),
+            None => None,
+        };
+        let words = match orth {
+            Some(orth) => lang.sorted_word_in(orth)?,
+            None => lang.sorted_word(),
+        };
+        for (i, word) in words.into_iter().filter(|(_, x)| x.matches(&filter)) {
+            let spelling = Cli::spelling(lang, word, orth)?;
+            println!("{}.\t{}", i, self.babel.summarize_spelling(word, &spelling));
+        }
+        Ok(())
+    }
+
+    fn execute_switch_m2u(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.m2u_idx(&key)?;
+        lang.enable_m2u(idx, enabled)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_switch_m2w(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        let orth = self.fetch_orth()?;
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.m2w_idx(orth, &key)?;
+        lang.enable_m2w(orth, idx, enabled)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_switch_mnt(&mut self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.mnt_idx(&key)?;
+        lang.enable_mnt(idx, enabled)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_reload(dir: &str) -> Result<(), Box<dyn Error>> {
+        let dir = if dir.is_empty() {
+            orth::default_dir()
+        } else {
+            PathBuf::from(dir)
+        };
+        orth::reload(&dir)?;
+        println!("Loaded orthography tables from {}", dir.display());
+        Ok(())
+    }
+
+    fn execute_mode(&mut self, mode: &str) -> Result<(), Box<dyn Error>> {
+        match mode {
+            "" => (),
+            "orth" => self.notation = None,
+            _ => self.notation = Some(mode.parse()?),
+        }
+        match self.notation {
+            Some(notation) => println!("IPA fields are read as {}; in patterns, regex syntax other than $()[] goes after ;", notation),
+            None => println!("IPA fields are read with orthography escapes"),
+        }
+        Ok(())
+    }
+
+    fn execute_cvt(&self, from: &str, to: &str, string: &str) -> Result<(), Box<dyn Error>> {
+        let ipa = match from {
+            "ipa" => string.to_owned(),
+            _ => sampa::from_ascii(string, from.parse()?, ""),
+        };
+        let converted = match to {
+            "ipa" => ipa,
+            _ => sampa::to_ascii(&ipa, to.parse()?),
+        };
+        println!("{}", converted);
+        Ok(())
+    }
+
+    fn execute_find(&self, query: &str) -> Result<(), Box<dyn Error>> {
+        let lang = self.cur_lang()?;
+        for (i, word) in lang.search(query) {
             println!("{}.\t{}", i, self.babel.summarize_word(word));
         }
         Ok(())
     }
 
+    fn execute_gloss(&mut self, codes: Vec<String>) -> Result<(), Box<dyn Error>> {
+        // with no codes this only reports; `gloss all` goes back to showing
+        // every language
+        let codes = match codes.as_slice() {
+            [] => None,
+            [x] if x == "all" => Some(Vec::new()),
+            _ => Some(codes),
+        };
+        if let Some(codes) = codes.filter(|x| x != self.babel.gloss_lang()) {
+            self.babel.set_gloss_lang(codes);
+            self.modify();
+        }
+        println!("Glosses shown: {}", match self.babel.gloss_lang().is_empty() {
+            true => String::from("all"),
+            false => self.babel.gloss_lang().join(" "),
+        });
+        Ok(())
+    }
+
+    fn execute_glossed(&mut self, codes: Vec<String>) -> Result<(), Box<dyn Error>> {
+        if codes.first().is_some_and(|x| x == "split") {
+            if self.babel.glossed_lang().is_empty() {
+                return Err(Box::new(CliError::InvalidInput));
+            }
+            let (split, skipped) = self.babel.split_gloss();
+            if split > 0 {
+                self.modify();
+            }
+            println!("Split {} glosses, {} didn't match the glossed languages", split, skipped);
+            return Ok(());
+        }
+        let codes = match codes.as_slice() {
+            [] => None,
+            [x] if x == "none" => Some(Vec::new()),
+            _ => Some(codes),
+        };
+        if let Some(codes) = codes.filter(|x| x != self.babel.glossed_lang()) {
+            self.babel.set_glossed_lang(codes);
+            self.modify();
+        }
+        println!("Glossed in: {}", match self.babel.glossed_lang().is_empty() {
+            true => String::from("(unmarked)"),
+            false => self.babel.glossed_lang().join(" "),
+        });
+        Ok(())
+    }
+
+    fn execute_norm(&mut self, norm: &str) -> Result<(), Box<dyn Error>> {
+        let norm = if norm.is_empty() {
+            self.babel.norm()
+        } else {
+            norm.parse()?
+        };
+        let changed = norm != self.babel.norm();
+        let count = self.babel.normalize(norm)?;
+        if changed || count > 0 {
+            self.modify();
+        }
+        println!("Normalized {} strings to {}", count, norm);
+        Ok(())
+    }
+
     fn execute_pwd(&self) -> Result<(), Box<dyn Error>> {
         let lang = self.cur_lang()?;
         println!("{}. {}", self.cur_lang.unwrap(), self.babel.summarize_lang(lang));
@@ -526,7 +1643,21 @@ impl Cli {
     }
 
     fn execute_revive(&mut self) -> Result<(), Box<dyn Error>> {
-        self.cur_lang_mut()?.revive();
+        self.cur_lang_mut()?.revive()?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_esc(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("key")?;
+        self.cur_lang_mut()?.rm_escape(&key)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_gesc(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("key")?;
+        self.babel.rm_escape(&key)?;
         self.modify();
         Ok(())
     }
@@ -536,27 +1667,66 @@ impl Cli {
         self.babel.rm_lang(idx)?;
         self.modify();
         if self.cur_lang == Some(idx) {
-            self.cur_lang = if self.babel.lang().len() > 0 { Some(0) } else { None };
+            self.cur_lang = if !self.babel.lang().is_empty() { Some(0) } else { None };
         }
         Ok(())
     }
 
     fn execute_rm_m2u(&mut self) -> Result<(), Box<dyn Error>> {
-        let idx = Cli::fetch_idx("index")?;
-        self.cur_lang_mut()?.rm_m2u(idx)?;
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.m2u_idx(&key)?;
+        lang.rm_m2u(idx)?;
         self.modify();
         Ok(())
     }
 
     fn execute_rm_m2w(&mut self) -> Result<(), Box<dyn Error>> {
+        let orth = self.fetch_orth()?;
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.m2w_idx(orth, &key)?;
+        lang.rm_m2w(orth, idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_glyph(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.glyph_idx(&key)?;
+        lang.rm_glyph(idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_tpl(&mut self) -> Result<(), Box<dyn Error>> {
         let idx = Cli::fetch_idx("index")?;
-        self.cur_lang_mut()?.rm_m2w(idx)?;
+        self.cur_lang_mut()?.rm_tpl(idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_class(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.class_idx(&key)?;
+        lang.rm_class(idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_orth(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.orth_idx(&key)?;
+        lang.rm_orth(idx)?;
         self.modify();
         Ok(())
     }
 
     fn execute_rm_cat(&mut self) -> Result<(), Box<dyn Error>> {
-        let name = Cli::fetch_char_int("name")?;
+        let name = self.fetch_char_int("name")?;
         let content = self.cur_lang_mut()?.rm_cat(name)?;
         println!("{}", content);
         self.modify();
@@ -564,8 +1734,17 @@ impl Cli {
     }
 
     fn execute_rm_mnt(&mut self) -> Result<(), Box<dyn Error>> {
-        let idx = Cli::fetch_idx("index")?;
-        self.cur_lang_mut()?.rm_mnt(idx)?;
+        let key = self.fetch("index")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.mnt_idx(&key)?;
+        lang.rm_mnt(idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_feat(&mut self) -> Result<(), Box<dyn Error>> {
+        let idx = self.babel.category_idx(&self.fetch("index")?)?;
+        self.babel.rm_category(idx)?;
         self.modify();
         Ok(())
     }
@@ -577,6 +1756,23 @@ impl Cli {
         Ok(())
     }
 
+    fn execute_rm_stg(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.fetch("stage")?;
+        let lang = self.cur_lang_mut()?;
+        let idx = lang.mnemonic_transform().stage_idx(&key)?;
+        lang.rm_stage(idx)?;
+        self.modify();
+        Ok(())
+    }
+
+    fn execute_rm_sense(&mut self) -> Result<(), Box<dyn Error>> {
+        let idx = Cli::fetch_idx("index")?;
+        let sense = Cli::fetch_idx("sense")?;
+        self.cur_lang_mut()?.word_at_mut(idx)?.rm_sense(sense)?;
+        self.modify();
+        Ok(())
+    }
+
     fn execute_rm_word(&mut self) -> Result<(), Box<dyn Error>> {
         let idx = Cli::fetch_idx("index")?;
         self.cur_lang_mut()?.rm_word(idx)?;
@@ -595,7 +1791,7 @@ impl Cli {
 
     fn execute_rst_pos(&mut self) -> Result<(), Box<dyn Error>> {
         let idx = Cli::fetch_idx("index")?;
-        let item = Cli::build_pos()?;
+        let item = self.build_pos()?;
         self.babel.alt_pos(idx, item)?;
         self.modify();
         Ok(())
@@ -620,40 +1816,85 @@ impl Cli {
     }
 
     fn step(&mut self) -> Result<bool, Box<dyn Error>> {
-        let mut buf = Cli::fetch("")?;
+        let mut buf = self.fetch("")?;
         if buf.trim() == "!!" {
             buf = self.last_command.clone();
         }
         let mut iter = buf.split_whitespace();
         match iter.next().unwrap_or("") {
             "add" => match iter.next().unwrap_or("") {
+                "esc" => self.execute_add_esc()?,
+                "gesc" => self.execute_add_gesc()?,
                 "lang" => self.execute_add_lang()?,
                 "m2u" => self.execute_add_m2u()?,
                 "m2w" => self.execute_add_m2w()?,
                 "cat" => self.execute_add_cat()?,
                 "mnt" => self.execute_add_mnt()?,
+                "orth" => self.execute_add_orth()?,
+                "tpl" => self.execute_add_tpl()?,
+                "cls" => self.execute_add_class()?,
+                "gly" => self.execute_add_glyph()?,
                 "pos" => self.execute_add_pos()?,
+                "feat" => self.execute_add_feat()?,
+                "stg" => self.execute_add_stg()?,
+                "sense" => self.execute_add_sense()?,
                 "word" => self.execute_add_word()?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
             "alt" => match iter.next().unwrap_or("") {
+                "abc" => self.execute_alt_abc()?,
+                "tac" => self.execute_alt_tac()?,
+                "gly" => self.execute_alt_glyph()?,
                 "lang" => self.execute_alt_lang()?,
                 "m2u" => self.execute_alt_m2u()?,
                 "m2w" => self.execute_alt_m2w()?,
                 "cat" => self.execute_alt_cat()?,
                 "mnt" => self.execute_alt_mnt()?,
+                "orth" => self.execute_alt_orth()?,
+                "tpl" => self.execute_alt_tpl()?,
+                "cls" => self.execute_alt_class()?,
+                "infl" => self.execute_alt_infl()?,
                 "pos" => self.execute_alt_pos()?,
+                "feat" => self.execute_alt_feat()?,
+                "sense" => self.execute_alt_sense()?,
                 "word" => self.execute_alt_word()?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
             "cat" => match iter.next().unwrap_or("word") {
-                "word" => self.execute_cat_word()?,
+                "guide" => self.execute_cat_guide()?,
+                "inv" => self.execute_cat_inv()?,
+                "stg" => self.execute_cat_stg()?,
+                "word" => self.execute_cat_word(iter.next().unwrap_or(""))?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
             "cd" => self.execute_cd()?,
+            "chk" => match iter.next().unwrap_or("") {
+                "m2w" => self.execute_chk_m2w()?,
+                "tac" => self.execute_chk_tac()?,
+                _ => return Err(Box::new(CliError::UnknownCommand))
+            }
+            "cvt" => {
+                let from = iter.next().ok_or(CliError::InvalidInput)?;
+                let to = iter.next().ok_or(CliError::InvalidInput)?;
+                self.execute_cvt(from, to, &iter.collect::<Vec<_>>().join(" "))?
+            }
             "dbg" => self.execute_debug()?,
             "drv" => self.execute_derive()?,
             "etym" => self.execute_etym()?,
+            "gen" => self.execute_gen(iter.next().unwrap_or(""))?,
+            "exp" => match iter.next().unwrap_or("") {
+                "guide" => self.execute_exp_guide(iter.next().ok_or(CliError::NullFile)?)?,
+                "inv" => self.execute_exp_inv(iter.next().unwrap_or(""), iter.next().ok_or(CliError::NullFile)?)?,
+                "mnt" => self.execute_exp_mnt(iter.next().unwrap_or(""), iter.next().ok_or(CliError::NullFile)?)?,
+                "stg" => self.execute_exp_stg()?,
+                "svg" => self.execute_exp_svg(iter.next().ok_or(CliError::NullFile)?)?,
+                "word" => self.execute_exp_word(iter.next().ok_or(CliError::NullFile)?)?,
+                _ => return Err(Box::new(CliError::UnknownCommand))
+            }
+            "imp" => match iter.next().unwrap_or("") {
+                "mnt" => self.execute_imp_mnt(iter.next().unwrap_or(""), iter.next().ok_or(CliError::NullFile)?)?,
+                _ => return Err(Box::new(CliError::UnknownCommand))
+            }
             "q" | ";" => {
                 self.check_modified()?;
                 return Ok(false);
@@ -665,26 +1906,56 @@ impl Cli {
                 "mnt" => self.execute_ins_mnt()?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
-            "int" => Cli::execute_int(iter.next().unwrap_or("")),
+            "int" => self.execute_int(iter.next().unwrap_or("")),
             "load" => self.execute_load(iter.next().ok_or(CliError::NullFile)?)?,
             "ls" => match iter.next().unwrap_or("word") {
+                "abc" => self.execute_ls_abc()?,
+                "tac" => self.execute_ls_tac()?,
+                "gly" => self.execute_ls_glyph()?,
+                "esc" => self.execute_ls_esc(),
                 "lang" => self.execute_ls_lang(),
-                "m2w" => self.execute_ls_m2w()?,
+                "m2w" => self.execute_ls_m2w(iter.next().unwrap_or(""))?,
                 "m2u" => self.execute_ls_m2u()?,
                 "cat" => self.execute_ls_cat()?,
                 "mnt" => self.execute_ls_mnt()?,
+                "orth" => self.execute_ls_orth()?,
+                "tpl" => self.execute_ls_tpl()?,
+                "cls" => self.execute_ls_class()?,
                 "pos" => self.execute_ls_pos(),
-                "word" => self.execute_ls_word()?,
+                "feat" => self.execute_ls_feat(),
+                "stg" => self.execute_ls_stg()?,
+                "word" => self.execute_ls_word(iter.collect())?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
+            cmd @ ("on" | "off") => match iter.next().unwrap_or("") {
+                "m2u" => self.execute_switch_m2u(cmd == "on")?,
+                "m2w" => self.execute_switch_m2w(cmd == "on")?,
+                "mnt" => self.execute_switch_mnt(cmd == "on")?,
+                _ => return Err(Box::new(CliError::UnknownCommand))
+            }
+            "find" => self.execute_find(&iter.collect::<Vec<_>>().join(" "))?,
+            "gloss" => self.execute_gloss(iter.map(String::from).collect())?,
+            "glossed" => self.execute_glossed(iter.map(String::from).collect())?,
+            "mode" => self.execute_mode(iter.next().unwrap_or(""))?,
+            "norm" => self.execute_norm(iter.next().unwrap_or(""))?,
+            "reload" => Cli::execute_reload(iter.next().unwrap_or(""))?,
             "pwd" => self.execute_pwd()?,
             "rm" | "del" => match iter.next().unwrap_or("") {
+                "esc" => self.execute_rm_esc()?,
+                "gesc" => self.execute_rm_gesc()?,
                 "lang" => self.execute_rm_lang()?,
                 "m2u" => self.execute_rm_m2u()?,
                 "m2w" => self.execute_rm_m2w()?,
                 "cat" => self.execute_rm_cat()?,
                 "mnt" => self.execute_rm_mnt()?,
+                "orth" => self.execute_rm_orth()?,
+                "tpl" => self.execute_rm_tpl()?,
+                "cls" => self.execute_rm_class()?,
+                "gly" => self.execute_rm_glyph()?,
                 "pos" => self.execute_rm_pos()?,
+                "feat" => self.execute_rm_feat()?,
+                "stg" => self.execute_rm_stg()?,
+                "sense" => self.execute_rm_sense()?,
                 "word" => self.execute_rm_word()?,
                 _ => return Err(Box::new(CliError::UnknownCommand))
             }
@@ -696,6 +1967,7 @@ impl Cli {
             }
             "rvv" => self.execute_revive()?,
             "save" => self.execute_save(iter.next().unwrap_or(""))?,
+            "xpr" => self.execute_xpr(iter.next().unwrap_or("")),
             "" => (),
             _ => return Err(Box::new(CliError::UnknownCommand))
         }
@@ -704,13 +1976,16 @@ impl Cli {
     }
 
     pub fn mainloop(&mut self) {
+        if let Err(err) = orth::reload(&orth::default_dir()) {
+            println!("Error occurred: {}", err);
+        }
         loop {
             match self.step() {
                 Ok(false) => break,
                 Ok(true) => (),
                 Err(err) => println!("Error occurred: {}", err),
             }
-            eprintln!("");
+            eprintln!();
         }
     }
 }
@@ -727,14 +2002,19 @@ impl Babel {
     }
 
     fn summarize_word(&self, word: &Word) -> String {
+        self.summarize_spelling(word, word.conlang())
+    }
+
+    fn summarize_spelling(&self, word: &Word, spelling: &str) -> String {
         let pos = match self.pos_at(word.pos()) {
             Ok(x) => x.abbr(),
             Err(_) => "?",
         };
-        format!("{:10}\t{:5}\t{:20}", word.conlang(), pos, word.natlang())
+        let gloss = word.gloss().map(|x| x.show(self.gloss_lang())).unwrap_or_default();
+        format!("{:10}\t{:5}\t{:20}", spelling, pos, gloss)
     }
 
-    fn illustrate_word(&self, word: &Word) -> String {
+    fn illustrate_word(&self, word: &Word, spelling: &str, orthographies: &[(&str, String)]) -> String {
         let pos = match self.pos_at(word.pos()) {
             Ok(x) => x.abbr(),
             Err(_) => "?",
@@ -748,15 +2028,39 @@ impl Babel {
                 Err(_) => ("?", "?"),
             }
         }).collect();
+        let variants: Vec<_> = word.variant().iter().map(|x| {
+            format!("{} [{}] ({})", x.conlang(), x.upa(), x.mnemonic())
+        }).collect();
+        let orthographies: Vec<_> = orthographies.iter().map(|(name, x)| format!("{}: {}", name, x)).collect();
+        let mut senses = String::new();
+        for (i, sense) in word.sense().iter().enumerate() {
+            let glosses: Vec<String> = sense.gloss().iter().map(|(code, text)| {
+                if code.is_empty() { text.to_owned() } else { format!("{}: {}", code, text) }
+            }).collect();
+            senses.push_str(&format!("\n{}. {}", i, glosses.join("; ")));
+            if !sense.register().is_empty() {
+                senses.push_str(&format!(" ({})", sense.register()));
+            }
+            if !word.ancestor().is_empty() {
+                senses.push_str(&format!(" [{}]", sense.shift()));
+            }
+            for (label, text) in [("definition", sense.definition()), ("usage", sense.note()), ("example", sense.example())] {
+                if !text.is_empty() {
+                    senses.push_str(&format!("\n\t{}:\t{}", label, text));
+                }
+            }
+        }
         format!(
-            "conlang:\t{}\nnatlang:\t{}\npart of speech:\t{}\nmnemonic:\t{}\nUPA:\t\t{}\ninformation:\t{}\nancestors:\t{:?}",
-            word.conlang(),
-            word.natlang(),
+            "conlang:\t{}\npart of speech:\t{}\nfeatures:\t{}\nmnemonic:\t{}\nUPA:\t\t{}\nancestors:\t{:?}\nvariants:\t{}\northography:\t{}\nsenses:{}",
+            spelling,
             pos,
+            Cli::show_feature(word.feature()),
             word.mnemonic(),
             word.upa(),
-            word.info(),
-            ancestors
+            ancestors,
+            variants.join(", "),
+            orthographies.join(", "),
+            senses
         )
     }
 }
\ No newline at end of file
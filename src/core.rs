@@ -1,16 +1,31 @@
 pub mod class;
+pub mod collation;
+pub mod dictionary;
+pub mod generator;
+pub mod guide;
+pub mod interchange;
+pub mod inventory;
+pub mod inverse;
 pub mod language;
 pub mod orth;
+pub mod phonotactics;
 pub mod pos;
+pub mod script;
 pub mod word;
 
+use class::Category;
+use indexmap::IndexMap;
 use language::Language;
+use orth::{Escape, Normalization};
 use pos::PoS;
+use word::Word;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 // use serde_json::Result as JsonResult;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 use std::fs::File;
+use std::io::Read;
 use std::path::PathBuf;
 
 #[macro_export]
@@ -34,29 +49,49 @@ macro_rules! build_path {
 
 #[derive(Debug)]
 pub enum BabelError {
-    // AdditionRejected,
-    // AlterationRejected,
+    AdditionRejected,
+    AlterationRejected,
+    BrokenRule(String),
     DeriveFromSelf,
     GhostWord(usize),
     IndexOutOfRange,
+    CyclicCategory(char),
+    CategoryInUse(char, String),
+    FeatureInUse(String, String),
     // InvalidCatagory(char),
     InvalidElement,
+    InvalidLabel(String),
     InvalidSCEnvironment,
     InvalidSCTarget,
+    MalformedOrth(String, usize),
+    MissingOrth(String),
+    NoAncestor,
+    UnknownFormat(String),
+    UnknownLabel(String),
 }
 
 impl Display for BabelError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            // BabelError::AdditionRejected => write!(f, "Addition is rejected."),
-            // BabelError::AlterationRejected => write!(f, "Alteration is rejected."),
+            BabelError::AdditionRejected => write!(f, "Addition is rejected."),
+            BabelError::AlterationRejected => write!(f, "Alteration is rejected."),
+            BabelError::BrokenRule(reason) => write!(f, "A rule no longer compiles: {}", reason),
             BabelError::DeriveFromSelf => write!(f, "Cannot derive from self!"),
             BabelError::GhostWord(idx) => write!(f, "Ghost word: {}", idx),
             BabelError::IndexOutOfRange => write!(f, "Index out of range!"),
+            BabelError::CyclicCategory(name) => write!(f, "Cyclic category: {}", name),
+            BabelError::CategoryInUse(name, users) => write!(f, "Category {} is used by {}", name, users),
+            BabelError::FeatureInUse(name, users) => write!(f, "Feature {} is used by {}", name, users),
             // BabelError::InvalidCatagory(name) => write!(f, "Invalid catagory: {}", name),
             BabelError::InvalidElement => write!(f, "Invalid element!"),
+            BabelError::InvalidLabel(label) => write!(f, "Label must be unique and not a number: {}", label),
             BabelError::InvalidSCEnvironment => write!(f, "Invalid SC environment!"),
             BabelError::InvalidSCTarget => write!(f, "Invalid SC target!"),
+            BabelError::MalformedOrth(file, line) => write!(f, "Malformed orthography table: {}:{}", file, line),
+            BabelError::MissingOrth(name) => write!(f, "Missing orthography table: {}", name),
+            BabelError::NoAncestor => write!(f, "The language has no ancestor."),
+            BabelError::UnknownFormat(name) => write!(f, "Unknown format: {}", name),
+            BabelError::UnknownLabel(label) => write!(f, "Unknown label: {}", label),
         }
     }
 }
@@ -67,6 +102,16 @@ impl Error for BabelError {}
 pub struct Babel {
     language: Vec<Option<Language>>,
     pos: Vec<Option<PoS>>,
+    #[serde(default)]
+    escape: Escape,
+    #[serde(default)]
+    norm: Normalization,
+    #[serde(default)]
+    gloss: Vec<String>,
+    #[serde(default)]
+    glossed: Vec<String>,
+    #[serde(default)]
+    category: Vec<Option<Category>>,
 }
 
 impl Babel {
@@ -74,15 +119,96 @@ impl Babel {
         Babel {
             language: Vec::new(),
             pos: Vec::new(),
+            escape: Escape::new(),
+            norm: Normalization::default(),
+            gloss: Vec::new(),
+            glossed: Vec::new(),
+            category: Vec::new(),
         }
     }
 
-    pub fn lang(&self) -> &Vec<Option<Language>> {
-        &self.language
+    /// Codes of the natural languages whose glosses are shown, in order.
+    pub fn gloss_lang(&self) -> &Vec<String> {
+        &self.gloss
+    }
+
+    pub fn set_gloss_lang(&mut self, codes: Vec<String>) {
+        self.gloss = codes;
+    }
+
+    /// Codes of the natural languages new glosses are written in, in order.
+    pub fn glossed_lang(&self) -> &Vec<String> {
+        &self.glossed
+    }
+
+    pub fn set_glossed_lang(&mut self, codes: Vec<String>) {
+        self.glossed = codes;
+    }
+
+    /// Splits unmarked "a / b" glosses into the glossed languages, returning
+    /// how many were split and how many didn't fit.
+    pub fn split_gloss(&mut self) -> (usize, usize) {
+        let codes = self.glossed.clone();
+        Babel::template_enum_mut(&mut self.language)
+            .map(|(_, x)| x.split_gloss(&codes))
+            .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1))
+    }
+
+    pub fn norm(&self) -> Normalization {
+        self.norm
+    }
+
+    // Object keys are left alone: category names are single characters and
+    // cannot take a decomposed form. Rule patterns are kept as typed and
+    // normalized when compiled, as NFD would split the classes they hold.
+    fn normalize_value(value: &mut Value, norm: Normalization, pattern: bool) -> usize {
+        const PATTERN: &[&str] = &["pat", "tg", "env", "onset", "coda", "cluster", "forbid"];
+        match value {
+            Value::String(_) if pattern => 0,
+            Value::String(string) => {
+                let normalized = norm.apply(string);
+                if normalized == *string {
+                    0
+                } else {
+                    *string = normalized;
+                    1
+                }
+            }
+            Value::Array(seq) => seq.iter_mut().map(|x| Babel::normalize_value(x, norm, pattern)).sum(),
+            Value::Object(map) => map.iter_mut().map(|(k, x)| Babel::normalize_value(x, norm, PATTERN.contains(&k.as_str()))).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Switches the normalization policy and rewrites every stored string but
+    /// rule patterns to match it, returning how many strings changed.
+    pub fn normalize(&mut self, norm: Normalization) -> Result<usize, Box<dyn Error>> {
+        self.norm = norm;
+        let mut value = serde_json::to_value(&*self)?;
+        let count = Babel::normalize_value(&mut value, norm, false);
+        if count > 0 {
+            *self = serde_json::from_value(value)?;
+        }
+        for (_, lang) in Babel::template_enum_mut(&mut self.language) {
+            lang.set_norm(norm);
+        }
+        Ok(count)
+    }
+
+    pub fn escape(&self) -> &Escape {
+        &self.escape
+    }
+
+    pub fn add_escape(&mut self, key: &str, value: &str) {
+        self.escape.insert(key.to_owned(), value.to_owned());
     }
 
-    pub fn pos(&self) -> &Vec<Option<PoS>> {
-        &self.pos
+    pub fn rm_escape(&mut self, key: &str) -> Result<String, BabelError> {
+        self.escape.remove(key).ok_or(BabelError::UnknownLabel(key.to_owned()))
+    }
+
+    pub fn lang(&self) -> &Vec<Option<Language>> {
+        &self.language
     }
 
     pub fn lang_at(&self, idx: usize) -> Result<&Language, BabelError> {
@@ -97,6 +223,51 @@ impl Babel {
         Babel::template_at(&self.pos, idx)
     }
 
+    pub fn category_at(&self, idx: usize) -> Result<&Category, BabelError> {
+        Babel::template_at(&self.category, idx)
+    }
+
+    pub fn category_idx(&self, key: &str) -> Result<usize, BabelError> {
+        match key.parse::<usize>() {
+            Ok(idx) if idx < self.category.len() => Ok(idx),
+            Ok(_) => Err(BabelError::IndexOutOfRange),
+            Err(_) => self.enum_category().find(|(_, x)| x.name() == key).map(|(i, _)| i).ok_or(BabelError::UnknownLabel(key.to_owned())),
+        }
+    }
+
+    /// The categories words of part of speech `pos` are marked for.
+    pub fn category_of(&self, pos: usize) -> impl Iterator<Item = &Category> {
+        self.enum_category().map(|(_, x)| x).filter(move |x| x.apply().contains(&pos))
+    }
+
+    /// Checks that every `category=value` pair names a category, one of its
+    /// values and, given `apply`, one of those parts of speech.
+    pub fn check_feature(&self, feature: &IndexMap<String, String>, apply: Option<&[usize]>) -> Result<(), BabelError> {
+        for (name, value) in feature {
+            let category = self.enum_category().map(|(_, x)| x)
+                .filter(|x| apply.is_none_or(|apply| apply.iter().any(|p| x.apply().contains(p))))
+                .find(|x| x.name() == name)
+                .ok_or_else(|| BabelError::UnknownLabel(name.to_owned()))?;
+            if !category.value().contains(value) {
+                return Err(BabelError::UnknownLabel(format!("{}={}", name, value)));
+            }
+        }
+        Ok(())
+    }
+
+    // Words and classes that would be left with stale features block the
+    // change; a rename alone is carried over to them.
+    fn check_users(&self, name: &str, category: Option<&Category>) -> Result<(), BabelError> {
+        let users: Vec<String> = self.enum_lang()
+            .flat_map(|(i, x)| x.feature_users(name, category).into_iter().map(move |u| format!("{} of language {}", u, i)))
+            .collect();
+        if users.is_empty() {
+            Ok(())
+        } else {
+            Err(BabelError::FeatureInUse(name.to_owned(), users.join(", ")))
+        }
+    }
+
     pub fn abbr_to_idx(&self, abbr: &str) -> Option<usize> {
         for (i, pos) in self.enum_pos() {
             if pos.abbr() == abbr {
@@ -106,7 +277,8 @@ impl Babel {
         None
     }
 
-    pub fn add_lang(&mut self, item: Language) {
+    pub fn add_lang(&mut self, mut item: Language) {
+        item.set_norm(self.norm);
         Babel::template_add(&mut self.language, item);
     }
 
@@ -114,11 +286,16 @@ impl Babel {
         Babel::template_add(&mut self.pos, item);
     }
 
+    pub fn add_category(&mut self, item: Category) {
+        Babel::template_add(&mut self.category, item);
+    }
+
     // pub fn alt_lang(&mut self, idx: usize, item: Language) -> Result<(), BabelError> {
     //     Babel::template_alt(&mut self.language, idx, item)
     // }
 
-    pub fn rst_lang(&mut self, idx: usize, item: Language) -> Result<(), BabelError> {
+    pub fn rst_lang(&mut self, idx: usize, mut item: Language) -> Result<(), BabelError> {
+        item.set_norm(self.norm);
         Babel::template_alt(&mut self.language, idx, item)
     }
 
@@ -126,6 +303,17 @@ impl Babel {
         Babel::template_alt(&mut self.pos, idx, item)
     }
 
+    pub fn alt_category(&mut self, idx: usize, item: Category) -> Result<(), BabelError> {
+        let old = self.category_at(idx)?.name().to_owned();
+        self.check_users(&old, Some(&item))?;
+        if old != item.name() {
+            for (_, lang) in Babel::template_enum_mut(&mut self.language) {
+                lang.rename_feature(&old, item.name());
+            }
+        }
+        Babel::template_alt(&mut self.category, idx, item)
+    }
+
     fn mut_and_ref(&mut self, mut_idx: usize, ref_idx: usize) -> Result<(&mut Language, &Language), BabelError> {
         if mut_idx == ref_idx {
             return Err(BabelError::DeriveFromSelf);
@@ -144,12 +332,33 @@ impl Babel {
         Ok((mut_item, ref_item))
     }
 
-    pub fn derive(&mut self, lang: usize, ancestor_idx: usize) -> Result<(), BabelError> {
+    pub fn derive(&mut self, lang: usize, ancestor_idx: usize, end: Option<usize>) -> Result<(), BabelError> {
         let (lang, ancestor) = self.mut_and_ref(lang, ancestor_idx)?;
-        lang.drv(ancestor_idx, ancestor)?;
+        lang.drv(ancestor_idx, ancestor, end)?;
         Ok(())
     }
 
+    pub fn copy_class(&mut self, lang: usize, ancestor_idx: usize) -> Result<(), BabelError> {
+        let (lang, ancestor) = self.mut_and_ref(lang, ancestor_idx)?;
+        lang.copy_class(ancestor)
+    }
+
+    pub fn glimpse(&self, lang: usize, end: usize) -> Result<Vec<(usize, Word)>, BabelError> {
+        let lang = self.lang_at(lang)?;
+        let ancestor = self.lang_at(lang.ancestor().ok_or(BabelError::NoAncestor)?)?;
+        lang.glimpse(ancestor, end)
+    }
+
+    pub fn split_lang(&mut self, lang: usize, stage: usize) -> Result<usize, BabelError> {
+        let source = self.lang_at(lang)?;
+        let ancestor_idx = source.ancestor().ok_or(BabelError::NoAncestor)?;
+        let stage = source.mnemonic_transform().stage().get(stage).ok_or(BabelError::IndexOutOfRange)?;
+        let mut item = source.until(&format!("{} ({})", source.name(), stage.name()), stage.end())?;
+        item.drv(ancestor_idx, self.lang_at(ancestor_idx)?, None)?;
+        self.add_lang(item);
+        Ok(self.language.len() - 1)
+    }
+
     pub fn enum_lang(&self) -> impl Iterator<Item = (usize, &Language)> {
         Babel::template_enum(&self.language)
     }
@@ -158,14 +367,22 @@ impl Babel {
         Babel::template_enum(&self.pos)
     }
 
+    pub fn enum_category(&self) -> impl Iterator<Item = (usize, &Category)> {
+        Babel::template_enum(&self.category)
+    }
+
     fn get_project(file: &str) -> PathBuf {
         let filename = format!("{}.json", file);
         build_path!("project", &filename)
     }
 
     pub fn load(file: &str) -> Result<Babel, Box<dyn Error>> {
-        let file = File::open(Babel::get_project(file))?;
-        let neo_babel: Babel = serde_json::from_reader(file)?;
+        Babel::read(File::open(Babel::get_project(file))?)
+    }
+
+    fn read(reader: impl Read) -> Result<Babel, Box<dyn Error>> {
+        let mut neo_babel: Babel = serde_json::from_reader(reader)?;
+        neo_babel.normalize(neo_babel.norm())?;
         Ok(neo_babel)
     }
 
@@ -177,6 +394,11 @@ impl Babel {
         Babel::template_rm(&mut self.pos, idx)
     }
 
+    pub fn rm_category(&mut self, idx: usize) -> Result<(), BabelError> {
+        self.check_users(self.category_at(idx)?.name(), None)?;
+        Babel::template_rm(&mut self.category, idx)
+    }
+
     pub fn save(&self, file: &str) -> Result<(), Box<dyn Error>> {
         let file = File::create(Babel::get_project(file))?;
         serde_json::to_writer_pretty(file, self)?;
@@ -220,45 +442,109 @@ impl Babel {
         seq.push(Some(item))
     }
 
-    fn template_alt<T>(seq: &mut Vec<Option<T>>, idx: usize, item: T) -> Result<(), BabelError> {
+    fn template_alt<T>(seq: &mut [Option<T>], idx: usize, item: T) -> Result<(), BabelError> {
         let old_item = seq.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
         *old_item = Some(item);
         Ok(())
     }
 
-    fn template_at<T>(seq: &Vec<Option<T>>, idx: usize) -> Result<&T, BabelError> {
+    fn template_at<T>(seq: &[Option<T>], idx: usize) -> Result<&T, BabelError> {
         let item = seq.get(idx).ok_or(BabelError::IndexOutOfRange)?;
         let item = item.as_ref().ok_or(BabelError::InvalidElement)?;
         Ok(item)
     }
 
-    fn template_at_mut<T>(seq: &mut Vec<Option<T>>, idx: usize) -> Result<&mut T, BabelError> {
+    fn template_at_mut<T>(seq: &mut [Option<T>], idx: usize) -> Result<&mut T, BabelError> {
         let item = seq.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
         let item = item.as_mut().ok_or(BabelError::InvalidElement)?;
         Ok(item)
     }
 
-    fn template_enum<T>(seq: &Vec<Option<T>>) -> impl Iterator<Item = (usize, &T)> {
-        seq.iter().enumerate().filter_map(|(idx, item)| {
-            match item.as_ref() {
-                Some(x) => Some((idx, x)),
-                None => None
-            }
-        })
+    fn template_enum<T>(seq: &[Option<T>]) -> impl Iterator<Item = (usize, &T)> {
+        seq.iter().enumerate().filter_map(|(idx, item)| item.as_ref().map(|x| (idx, x)))
     }
 
-    fn template_enum_mut<T>(seq: &mut Vec<Option<T>>) -> impl Iterator<Item = (usize, &mut T)> {
-        seq.iter_mut().enumerate().filter_map(|(idx, item)| {
-            match item.as_mut() {
-                Some(x) => Some((idx, x)),
-                None => None
-            }
-        })
+    fn template_enum_mut<T>(seq: &mut [Option<T>]) -> impl Iterator<Item = (usize, &mut T)> {
+        seq.iter_mut().enumerate().filter_map(|(idx, item)| item.as_mut().map(|x| (idx, x)))
     }
 
-    fn template_rm<T>(seq: &mut Vec<Option<T>>, idx: usize) -> Result<(), BabelError> {
+    fn template_rm<T>(seq: &mut [Option<T>], idx: usize) -> Result<(), BabelError> {
         let old_item = seq.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
         *old_item = None;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use language::SoundChange;
+    use word::Word;
+
+    fn sample() -> Babel {
+        let mut babel = Babel::new();
+        let mut lang = Language::new("Test");
+        let mut sca = language::SCA::new();
+        sca.add_cat('V', "áe").unwrap();
+        sca.add_sc(SoundChange::new("[áé]", "i", "_t")).unwrap();
+        lang.set_mnemonic_transform(sca);
+        lang.add_word(Word::shell("rát", 0)).unwrap();
+        babel.add_lang(lang);
+        babel
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut babel = sample();
+        assert_eq!(babel.normalize(Normalization::Nfd).unwrap(), 4);
+        let lang = babel.lang_at(0).unwrap();
+        assert_eq!(lang.norm(), Normalization::Nfd);
+        assert_eq!(lang.mnemonic_transform().cat()[&'V'], "a\u{301}e");
+        assert_eq!(lang.mnemonic_transform().sc()[0].tg(), "[áé]");
+        assert_eq!(lang.word_at(0).unwrap().mnemonic(), "ra\u{301}t");
+        let sub = &lang.mnemonic_transform().compile_all().unwrap()[0];
+        assert_eq!(sub.pat().replace_all(lang.word_at(0).unwrap().mnemonic(), sub.repl()), "rit");
+        babel.normalize(Normalization::Nfc).unwrap();
+        assert_eq!(babel.lang_at(0).unwrap().word_at(0).unwrap().mnemonic(), "rát");
+    }
+
+    #[test]
+    fn test_read() {
+        let mut babel = sample();
+        babel.normalize(Normalization::Nfd).unwrap();
+        let json = serde_json::to_string(&babel).unwrap().replace("ra\\u0301t", "rát");
+        let babel = Babel::read(json.as_bytes()).unwrap();
+        let lang = babel.lang_at(0).unwrap();
+        assert_eq!(lang.norm(), Normalization::Nfd);
+        assert_eq!(lang.word_at(0).unwrap().mnemonic(), "ra\u{301}t");
+        assert_eq!(lang.mnemonic_transform().sc()[0].tg(), "[áé]");
+    }
+
+    #[test]
+    fn test_split_lang() {
+        let mut babel = sample();
+        babel.add_lang(Language::new("Daughter"));
+        babel.derive(1, 0, None).unwrap();
+        let daughter = babel.lang_at_mut(1).unwrap();
+        daughter.add_mnt(SoundChange::new("t", "d", "_")).unwrap();
+        daughter.add_stage(language::Stage::new("Old", 1)).unwrap();
+        assert!(babel.split_lang(1, 1).is_err());
+        assert_eq!(babel.split_lang(1, 0).unwrap(), 2);
+        let daughter = babel.lang_at_mut(1).unwrap();
+        daughter.add_cat('U', "ao").unwrap();
+        daughter.add_cat('W', "ei").unwrap();
+        daughter.add_m2u(language::Replace::new("U", "W").unwrap().with_env("_t")).unwrap();
+        daughter.rm_cat('U').unwrap();
+        assert!(babel.split_lang(1, 0).is_err());
+        assert_eq!(babel.enum_lang().count(), 3);
+    }
+
+    #[test]
+    fn test_category_idx() {
+        let mut babel = Babel::new();
+        babel.add_category(Category::new("gender", vec![0], vec![String::from("m"), String::from("f")]));
+        assert_eq!(babel.category_idx("gender").unwrap(), 0);
+        assert_eq!(babel.category_idx("0").unwrap(), 0);
+        assert!(matches!(babel.category_idx("1"), Err(BabelError::IndexOutOfRange)));
+        assert!(matches!(babel.category_idx("case"), Err(BabelError::UnknownLabel(_))));
+    }
+}
@@ -1,13 +1,352 @@
 use serde::{Deserialize, Serialize};
 // use serde_json::Result as JsonResult;
+use indexmap::IndexMap;
+use super::BabelError;
+use super::language::{Language, SoundChange};
+use super::word::Word;
 
+/// One slot of a paradigm. `~` in the template stands for the stem, so
+/// `~en` is a suffix and `ge~t` a circumfix; `change`, if any, then applies
+/// to the whole form, e.g. for umlaut.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Cell {
+    name: String,
+    template: String,
+    #[serde(default)]
+    change: Option<SoundChange>,
+}
+
+impl Cell {
+    pub fn new(name: &str, template: &str) -> Cell {
+        Cell { name: name.to_owned(), template: template.to_owned(), change: None }
+    }
+
+    pub fn with_change(mut self, change: Option<SoundChange>) -> Cell {
+        self.change = change;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn change(&self) -> Option<&SoundChange> {
+        self.change.as_ref()
+    }
+}
+
+/// A grammatical category such as gender or transitivity, with the values
+/// words of the parts of speech in `apply` can take.
 #[derive(Deserialize, Serialize, Debug)]
-struct Class {
+pub struct Category {
     name: String,
     apply: Vec<usize>,
-    values: Vec<String>,
+    value: Vec<String>,
+}
+
+impl Category {
+    pub fn new(name: &str, apply: Vec<usize>, value: Vec<String>) -> Category {
+        Category { name: name.to_owned(), apply, value }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn apply(&self) -> &Vec<usize> {
+        &self.apply
+    }
+
+    pub fn value(&self) -> &Vec<String> {
+        &self.value
+    }
+}
+
+/// An inflection class: the paradigm shared by words of the parts of speech
+/// in `apply` that have every value in `feature`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Class {
+    name: String,
+    apply: Vec<usize>,
+    #[serde(default)]
+    feature: IndexMap<String, String>,
+    #[serde(default)]
+    cell: Vec<Cell>,
 }
 
 impl Class {
+    pub fn new(name: &str, apply: Vec<usize>, cell: Vec<Cell>) -> Class {
+        Class { name: name.to_owned(), apply, feature: IndexMap::new(), cell }
+    }
+
+    pub fn with_feature(mut self, feature: IndexMap<String, String>) -> Class {
+        self.feature = feature;
+        self
+    }
+
+    pub fn feature(&self) -> &IndexMap<String, String> {
+        &self.feature
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn apply(&self) -> &Vec<usize> {
+        &self.apply
+    }
+
+    pub fn cell(&self) -> &Vec<Cell> {
+        &self.cell
+    }
+
+    pub(super) fn rename_feature(&mut self, old: &str, new: &str) {
+        if let Some(value) = self.feature.shift_remove(old) {
+            self.feature.insert(new.to_owned(), value);
+        }
+    }
+}
 
-}
\ No newline at end of file
+pub struct Form {
+    cell: String,
+    mnemonic: String,
+    conlang: String,
+    upa: String,
+    irregular: bool,
+}
+
+impl Form {
+    pub fn cell(&self) -> &str {
+        &self.cell
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    pub fn conlang(&self) -> &str {
+        &self.conlang
+    }
+
+    pub fn upa(&self) -> &str {
+        &self.upa
+    }
+
+    /// Whether the form overrides what the class template gives.
+    pub fn irregular(&self) -> bool {
+        self.irregular
+    }
+}
+
+impl Language {
+    pub fn class_idx(&self, key: &str) -> Result<usize, BabelError> {
+        if let Ok(idx) = key.parse() {
+            return Ok(idx);
+        }
+        self.enum_class().find(|(_, x)| x.name == key).map(|(i, _)| i).ok_or(BabelError::UnknownLabel(key.to_owned()))
+    }
+
+    /// The class a word inflects by: the one it names, or else the first
+    /// class for its part of speech whose features it has.
+    pub fn class_of(&self, word: &Word) -> Option<&Class> {
+        match word.class() {
+            "" => self.enum_class().map(|(_, x)| x).find(|x| x.apply.contains(&word.pos()) && word.matches(&x.feature)),
+            name => self.enum_class().map(|(_, x)| x).find(|x| x.name == name),
+        }
+    }
+
+    fn inflect(&self, cell: &Cell, stem: &str) -> Result<String, BabelError> {
+        let form = self.norm().apply(&cell.template).replace('~', stem);
+        match &cell.change {
+            Some(sc) => {
+                let subs = self.mnemonic_transform().compile(sc).map_err(|x| BabelError::BrokenRule(x.to_string()))?;
+                Ok(Word::render(&form, &subs))
+            }
+            None => Ok(form),
+        }
+    }
+
+    // Each cell with the mnemonics filling it, every variant kept, and
+    // whether they're an override. Overrides for cells the class doesn't
+    // have, such as those a derived word brought from its ancestor, come last.
+    pub(super) fn paradigm_mnemonic(&self, word: &Word) -> Result<Vec<(String, Vec<String>, bool)>, BabelError> {
+        let mut forms: IndexMap<String, (Vec<String>, bool)> = IndexMap::new();
+        if let Some(class) = self.class_of(word) {
+            for cell in class.cell.iter() {
+                forms.insert(cell.name.clone(), (vec![self.inflect(cell, word.mnemonic())?], false));
+            }
+        }
+        for (cell, derived) in word.derived() {
+            forms.insert(cell.clone(), (derived.clone(), true));
+        }
+        for (cell, mnemonic) in word.inflection() {
+            forms.insert(cell.clone(), (vec![mnemonic.clone()], true));
+        }
+        Ok(forms.into_iter().map(|(cell, (mnemonic, irregular))| (cell, mnemonic, irregular)).collect())
+    }
+
+    /// One form per cell and variant, variants right after their cell.
+    pub fn paradigm(&self, word: &Word) -> Result<Vec<Form>, BabelError> {
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
+        let mut paradigm = Vec::new();
+        for (cell, forms, irregular) in self.paradigm_mnemonic(word)? {
+            for mnemonic in forms {
+                paradigm.push(Form {
+                    conlang: Word::render(&mnemonic, &m2w),
+                    upa: Word::render(&mnemonic, &m2u),
+                    cell: cell.clone(),
+                    mnemonic,
+                    irregular,
+                });
+            }
+        }
+        Ok(paradigm)
+    }
+
+    /// Takes over the ancestor's classes, e.g. for a daughter that had
+    /// none when it was derived.
+    pub fn copy_class(&mut self, ancestor: &Language) -> Result<(), BabelError> {
+        for class in ancestor.enum_class().map(|(_, x)| x) {
+            self.add_class(class.clone());
+        }
+        let idx: Vec<usize> = self.enum_word().map(|(i, _)| i).collect();
+        for i in idx {
+            self.prune_derived(i)?;
+        }
+        Ok(())
+    }
+
+    /// Drops derived forms the class template gives anyway, so a derived
+    /// word only keeps the cells sound change made irregular.
+    pub(super) fn prune_derived(&mut self, idx: usize) -> Result<(), BabelError> {
+        let word = self.word_at(idx)?;
+        let mut regular = Vec::new();
+        if let Some(class) = self.class_of(word) {
+            for cell in class.cell.iter() {
+                if word.derived().get(&cell.name) == Some(&vec![self.inflect(cell, word.mnemonic())?]) {
+                    regular.push(cell.name.clone());
+                }
+            }
+        }
+        let word = self.word_at_mut(idx)?;
+        for cell in regular {
+            word.set_derived(&cell, Vec::new());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::Babel;
+
+    #[test]
+    fn test_paradigm() {
+        let mut proto = Language::new("Proto");
+        proto.add_class(Class::new("a-stem", vec![0], vec![Cell::new("sg", "~"), Cell::new("pl", "~i")]));
+        proto.add_word(Word::shell("kata", 0)).unwrap();
+        let mut irregular = Word::shell("pa", 0);
+        irregular.set_inflection("pl", "pe");
+        proto.add_word(irregular).unwrap();
+        let forms: Vec<_> = proto.paradigm(proto.word_at(1).unwrap()).unwrap().into_iter()
+            .map(|x| (x.mnemonic, x.irregular))
+            .collect();
+        assert_eq!(forms, vec![(String::from("pa"), false), (String::from("pe"), true)]);
+
+        let mut daughter = proto.until("Daughter", 0).unwrap();
+        daughter.add_mnt(SoundChange::new("t", "d", "[aeiou]_[aeiou]")).unwrap();
+        daughter.add_mnt(SoundChange::new("ai", "e", "_")).unwrap();
+        daughter.drv(0, &proto, None).unwrap();
+        let kata = daughter.word_at(0).unwrap();
+        let forms: Vec<_> = daughter.paradigm(kata).unwrap().into_iter()
+            .map(|x| (x.mnemonic, x.irregular))
+            .collect();
+        assert_eq!(forms, vec![(String::from("kada"), false), (String::from("kade"), true)]);
+        let pa = daughter.word_at(1).unwrap();
+        assert_eq!(daughter.paradigm(pa).unwrap()[1].mnemonic(), "pe");
+
+        let mut kata = daughter.word_at(0).unwrap().clone();
+        kata.set_inflection("du", "kadu");
+        daughter.alt_word(0, kata).unwrap();
+        daughter.drv(0, &proto, None).unwrap();
+        let kata = daughter.word_at(0).unwrap();
+        assert_eq!(kata.inflection().get("du").map(|x| x.as_str()), Some("kadu"));
+        assert_eq!(kata.derived().get("pl"), Some(&vec![String::from("kade")]));
+    }
+
+    #[test]
+    fn test_rederive() {
+        let mut proto = Language::new("Proto");
+        proto.add_class(Class::new("a-stem", vec![0], vec![Cell::new("sg", "~"), Cell::new("pl", "~i")]));
+        proto.add_word(Word::shell("kata", 0)).unwrap();
+        let mut daughter = Language::new("Daughter");
+        daughter.add_mnt(SoundChange::new("ai", "e", "_")).unwrap();
+        daughter.drv(0, &proto, None).unwrap();
+        assert!(daughter.enum_class().next().is_none());
+        let mut kata = daughter.word_at(0).unwrap().clone();
+        kata.set_inflection("du", "katu");
+        daughter.alt_word(0, kata).unwrap();
+        daughter.alt_mnt(0, SoundChange::new("ai", "o", "_").with_optional(true)).unwrap();
+        daughter.drv(0, &proto, None).unwrap();
+        daughter.copy_class(&proto).unwrap();
+        let kata = daughter.word_at(0).unwrap();
+        let forms: Vec<_> = daughter.paradigm(kata).unwrap().into_iter()
+            .map(|x| (x.cell, x.mnemonic))
+            .collect();
+        let expected = [("sg", "kata"), ("pl", "kato"), ("pl", "katai"), ("du", "katu")];
+        assert_eq!(forms, expected.map(|(x, y)| (String::from(x), String::from(y))));
+    }
+
+    #[test]
+    fn test_change_cell() {
+        let mut lang = Language::new("Test");
+        lang.add_cat('V', "ao").unwrap();
+        lang.add_cat('F', "ei").unwrap();
+        lang.add_cat('C', "kt").unwrap();
+        let umlaut = SoundChange::new("V", "F", "_C*$");
+        lang.add_class(Class::new("umlaut", vec![0], vec![Cell::new("pl", "~").with_change(Some(umlaut))]));
+        let word = Word::shell("kat", 0);
+        assert_eq!(lang.paradigm(&word).unwrap()[0].mnemonic(), "ket");
+    }
+
+    #[test]
+    fn test_feature() {
+        let mut lang = Language::new("Test");
+        let feminine = IndexMap::from([(String::from("gender"), String::from("f"))]);
+        lang.add_class(Class::new("feminine", vec![0], vec![Cell::new("pl", "~e")]).with_feature(feminine.clone()));
+        lang.add_class(Class::new("masculine", vec![0], vec![Cell::new("pl", "~i")]));
+        let mut word = Word::shell("lun", 0);
+        assert_eq!(lang.class_of(&word).map(|x| x.name()), Some("masculine"));
+        word.set_feature("gender", "f");
+        assert!(word.matches(&feminine));
+        assert_eq!(lang.paradigm(&word).unwrap()[0].mnemonic(), "lune");
+        word.set_feature("gender", "");
+        assert!(word.feature().is_empty());
+    }
+
+    #[test]
+    fn test_category_edit() {
+        let mut babel = Babel::new();
+        let gender = |name: &str, value: &str| Category::new(name, vec![0], value.split_whitespace().map(String::from).collect());
+        babel.add_category(gender("gender", "m f"));
+        let mut word = Word::shell("lun", 0);
+        word.set_feature("gender", "f");
+        let mut lang = Language::new("Test");
+        lang.add_word(word).unwrap();
+        babel.add_lang(lang);
+        let feminine = IndexMap::from([(String::from("gender"), String::from("f"))]);
+        assert!(babel.check_feature(&feminine, Some(&[0])).is_ok());
+        assert!(babel.check_feature(&feminine, Some(&[1])).is_err());
+        assert!(babel.check_feature(&IndexMap::from([(String::from("gender"), String::from("n"))]), None).is_err());
+        assert!(babel.alt_category(0, gender("gender", "m")).is_err());
+        assert!(babel.rm_category(0).is_err());
+        babel.alt_category(0, gender("genus", "m f")).unwrap();
+        assert_eq!(babel.lang_at(0).unwrap().word_at(0).unwrap().feature().get("genus").map(|x| x.as_str()), Some("f"));
+    }
+}
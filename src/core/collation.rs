@@ -0,0 +1,97 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
+use super::BabelError;
+use super::language::Language;
+use super::word::Word;
+
+/// Sort key of a written form: letters first, then the diacritics left over
+/// after matching them, then the form itself to break ties on case.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct CollationKey {
+    primary: Vec<u32>,
+    secondary: Vec<Vec<char>>,
+    tertiary: String,
+}
+
+impl Language {
+    pub fn collation_key(&self, text: &str) -> CollationKey {
+        let letters: Vec<Vec<char>> = self.alphabet().iter().map(|x| x.to_lowercase().nfd().collect()).collect();
+        let offset = letters.len() as u32;
+        let chars: Vec<char> = text.to_lowercase().nfd().collect();
+        let mut primary = Vec::new();
+        let mut secondary = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let found = letters.iter().enumerate()
+                .filter(|(_, x)| !x.is_empty() && chars[i..].starts_with(x))
+                .max_by_key(|(_, x)| x.len());
+            let len = match found {
+                Some((rank, letter)) => {
+                    primary.push(rank as u32);
+                    letter.len()
+                }
+                None if canonical_combining_class(chars[i]) != 0 => 0,
+                None => {
+                    primary.push(offset + chars[i] as u32);
+                    1
+                }
+            };
+            i += len;
+            let marks: Vec<char> = chars[i..].iter().copied().take_while(|&x| canonical_combining_class(x) != 0).collect();
+            i += marks.len();
+            secondary.push(marks);
+        }
+        CollationKey { primary, secondary, tertiary: text.to_owned() }
+    }
+
+    fn sort_key(&self, written: &str, word: &Word) -> (CollationKey, Option<String>) {
+        (self.collation_key(written), word.gloss().map(|x| x.show(&[])))
+    }
+
+    pub fn sorted_word(&self) -> Vec<(usize, &Word)> {
+        let mut words: Vec<_> = self.enum_word().collect();
+        words.sort_by_cached_key(|(_, x)| self.sort_key(x.conlang(), x));
+        words
+    }
+
+    /// Words in the order of their spelling in a named orthography.
+    pub fn sorted_word_in(&self, orth: usize) -> Result<Vec<(usize, &Word)>, BabelError> {
+        let rule = self.make_orth(orth)?;
+        let mut words: Vec<_> = self.enum_word().collect();
+        words.sort_by_cached_key(|(_, x)| self.sort_key(&x.spell(&rule), x));
+        Ok(words)
+    }
+
+    /// Words with a gloss containing `query` in any language, in dictionary
+    /// order.
+    pub fn search(&self, query: &str) -> Vec<(usize, &Word)> {
+        self.sorted_word().into_iter().filter(|(_, x)| x.glossed(query)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::language::{Orthography, Replace};
+
+    #[test]
+    fn test_collation() {
+        let mut lang = Language::new("Test");
+        lang.set_alphabet("a c ch e h n ñ o".split(' ').map(String::from).collect());
+        let mut forms = vec!["ño", "cha", "ca", "cá", "ha", "na", "Ca"];
+        forms.sort_by_key(|x| lang.collation_key(x));
+        assert_eq!(forms, vec!["Ca", "ca", "cá", "cha", "ha", "na", "ño"]);
+    }
+
+    #[test]
+    fn test_sorted_in_orthography() {
+        let mut lang = Language::new("Test");
+        lang.add_orth(Orthography::new("Reversed"));
+        lang.add_m2w(Some(0), Replace::new("a", "z").unwrap()).unwrap();
+        lang.add_word(Word::shell("b", 0)).unwrap();
+        lang.add_word(Word::shell("ab", 0)).unwrap();
+        let order = |words: Vec<(usize, &Word)>| words.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(order(lang.sorted_word()), vec![1, 0]);
+        assert_eq!(order(lang.sorted_word_in(0).unwrap()), vec![0, 1]);
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt::Write;
+use indexmap::IndexSet;
+use super::{Babel, BabelError};
+use super::language::Language;
+use super::orth::{self, Escape};
+use super::word::{Sense, Shift};
+
+fn cell(text: &str) -> String {
+    text.replace(['\t', '\n'], " ")
+}
+
+impl Babel {
+    pub fn export_vocab(&self, lang: &Language) -> Result<String, BabelError> {
+        let overlays: Vec<&Escape> = vec![self.escape(), lang.escape()];
+        // one gloss column per natural language shown, or per language the
+        // lexicon glosses in at all
+        let codes: IndexSet<&str> = if self.gloss_lang().is_empty() {
+            lang.enum_word().flat_map(|(_, x)| x.sense().iter()).flat_map(|x| x.gloss().iter().map(|(code, _)| code)).collect()
+        } else {
+            self.gloss_lang().iter().map(|x| x.as_str()).collect()
+        };
+        let mut out = String::from("index\tmnemonic\tconlang\tupa\tpos");
+        for code in codes.iter() {
+            if code.is_empty() {
+                out.push_str("\tnatlang");
+            } else {
+                write!(out, "\tnatlang:{}", cell(code)).unwrap();
+            }
+        }
+        out.push_str("\tdefinition\tnote\tregister\texample");
+        for (_, orth) in lang.enum_orth() {
+            write!(out, "\t{}", cell(orth.name())).unwrap();
+        }
+        out.push('\n');
+        for (i, word) in lang.sorted_word() {
+            let pos = match self.pos_at(word.pos()) {
+                Ok(x) => x.abbr(),
+                Err(_) => "?",
+            };
+            write!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                i,
+                cell(&orth::express_with(word.mnemonic(), &overlays)),
+                cell(word.conlang()),
+                cell(word.upa()),
+                cell(pos)
+            ).unwrap();
+            // senses the word still has, lined up column by column
+            let senses: Vec<&Sense> = word.sense().iter().filter(|x| x.shift() != Shift::Lost).collect();
+            for code in codes.iter() {
+                let texts: Vec<&str> = senses.iter().filter_map(|x| x.gloss().text(code)).collect();
+                write!(out, "\t{}", cell(&texts.join("; "))).unwrap();
+            }
+            let fields: [fn(&Sense) -> &str; 4] = [Sense::definition, Sense::note, Sense::register, Sense::example];
+            for field in fields {
+                let texts: Vec<&str> = senses.iter().map(|x| field(x)).collect();
+                write!(out, "\t{}", cell(&texts.join("; "))).unwrap();
+            }
+            for (_, spelling) in lang.spell_all(word)? {
+                write!(out, "\t{}", cell(&spelling)).unwrap();
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
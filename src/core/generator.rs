@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+use indexmap::IndexMap;
+use rand::Rng;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use serde::{Deserialize, Serialize};
+use super::BabelError;
+use super::language::Language;
+use super::orth::{self, Normalization};
+use super::word::Word;
+
+// Attempts allowed per requested candidate before giving up, so a template
+// that can only produce taken or forbidden forms doesn't loop forever.
+const ATTEMPTS: usize = 100;
+
+/// A word shape to coin roots from, such as `CV(C)CV`: category names stand
+/// for any of their units, parentheses mark optional parts, and anything
+/// else is copied as it is.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Template {
+    pattern: String,
+    #[serde(default = "Template::default_weight")]
+    weight: u32,
+}
+
+impl Template {
+    pub fn new(pattern: &str, weight: u32) -> Template {
+        Template { pattern: pattern.to_owned(), weight }
+    }
+
+    fn default_weight() -> u32 {
+        1
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    fn fill<R: Rng>(chars: &[char], cat: &IndexMap<char, String>, rng: &mut R, out: &mut String) {
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '(' => {
+                    let mut depth = 0;
+                    let end = (i..chars.len()).find(|&j| {
+                        match chars[j] {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => (),
+                        }
+                        depth == 0
+                    }).unwrap_or(chars.len());
+                    if rng.gen_bool(0.5) {
+                        Template::fill(&chars[i + 1..end], cat, rng, out);
+                    }
+                    i = end + 1;
+                    continue;
+                }
+                x => match cat.get(&x) {
+                    Some(content) => {
+                        let units = orth::clusters(content);
+                        if !units.is_empty() {
+                            out.push_str(units[rng.gen_range(0..units.len())]);
+                        }
+                    }
+                    None => out.push(x),
+                },
+            }
+            i += 1;
+        }
+    }
+
+    fn generate<R: Rng>(&self, cat: &IndexMap<char, String>, norm: Normalization, rng: &mut R) -> String {
+        let chars: Vec<char> = norm.apply(&self.pattern).chars().collect();
+        let mut out = String::new();
+        Template::fill(&chars, cat, rng, &mut out);
+        out
+    }
+}
+
+impl Language {
+    /// Coins up to `count` new mnemonics, picking templates by weight and
+    /// dropping forms the lexicon already has or the phonotactics forbid.
+    pub fn generate<R: Rng>(&self, count: usize, rng: &mut R) -> Result<Vec<String>, BabelError> {
+        let templates: Vec<_> = self.enum_tpl().map(|(_, x)| x).filter(|x| x.weight > 0).collect();
+        let dist = match WeightedIndex::new(templates.iter().map(|x| x.weight)) {
+            Ok(dist) => dist,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let cat = self.mnemonic_transform().normalized_cat().unwrap_or_default();
+        let mut taken: BTreeSet<String> = self.enum_word().map(|(_, x)| x.mnemonic().to_owned()).collect();
+        let validate = self.validator()?;
+        let mut coined = Vec::new();
+        for _ in 0..count * ATTEMPTS {
+            if coined.len() >= count {
+                break;
+            }
+            let form = templates[dist.sample(rng)].generate(&cat, self.norm(), rng);
+            if form.is_empty() || taken.contains(&form) || !validate(&form).is_empty() {
+                continue;
+            }
+            taken.insert(form.clone());
+            coined.push(form);
+        }
+        Ok(coined)
+    }
+
+    /// How a mnemonic would be written and pronounced, without adding it.
+    pub fn preview(&self, mnemonic: &str) -> Result<Word, BabelError> {
+        let mut word = Word::shell(mnemonic, 0);
+        word.morph(&self.make_m2w()?, &self.make_m2u()?);
+        Ok(word)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::core::phonotactics::Phonotactics;
+
+    #[test]
+    fn test_generate() {
+        let mut lang = Language::new("Test");
+        lang.add_cat('C', "pt").unwrap();
+        lang.add_cat('V', "a").unwrap();
+        lang.add_tpl(Template::new("CV(C)", 1));
+        lang.add_word(Word::shell("pa", 0)).unwrap();
+        lang.set_phonotactics(Phonotactics::new('V', "").with_forbid(vec![String::from("tat")]));
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut coined = lang.generate(10, &mut rng).unwrap();
+        coined.sort();
+        assert_eq!(coined, vec!["pap", "pat", "ta", "tap"]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use indexmap::IndexMap;
+use super::BabelError;
+use super::inverse::literal_pairs;
+use super::language::{Language, Replace};
+
+const EXAMPLES: usize = 3;
+
+pub struct Sound {
+    ipa: String,
+    context: String,
+    example: Vec<usize>,
+}
+
+impl Sound {
+    pub fn ipa(&self) -> &str {
+        &self.ipa
+    }
+
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    pub fn example(&self) -> &Vec<usize> {
+        &self.example
+    }
+}
+
+pub struct Letter {
+    written: String,
+    mnemonic: String,
+    sound: Vec<Sound>,
+}
+
+impl Letter {
+    pub fn written(&self) -> &str {
+        &self.written
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    pub fn sound(&self) -> &Vec<Sound> {
+        &self.sound
+    }
+}
+
+impl Language {
+    /// Pairs every letter `mnemonic_to_word` writes with the sounds
+    /// `mnemonic_to_upa` gives the same mnemonic. Only rules that rewrite
+    /// plain strings take part; mnemonic characters no spelling rule touches
+    /// are taken to be written as themselves.
+    pub fn pronunciation_guide(&self) -> Result<Vec<Letter>, BabelError> {
+        let cat = self.mnemonic_transform().normalized_cat().unwrap_or_default();
+        let m2w: Vec<&Replace> = self.enum_m2w(None).unwrap().map(|(_, x)| x).filter(|x| x.meta().enabled()).collect();
+        let m2u: Vec<&Replace> = self.enum_m2u().map(|(_, x)| x).filter(|x| x.meta().enabled()).collect();
+        let m2u_subs = m2u.iter().map(|x| self.make_replace(std::slice::from_ref(*x))).collect::<Result<Vec<_>, _>>()?;
+        let mut letters: IndexMap<(String, String), ()> = IndexMap::new();
+        for rule in m2w.iter() {
+            for (mnemonic, written) in literal_pairs(rule, &cat, self.norm()).unwrap_or_default() {
+                letters.insert((written, mnemonic), ());
+            }
+        }
+        let spelt: BTreeSet<char> = m2w.iter().flat_map(|x| self.norm().apply(x.pat()).chars().collect::<Vec<_>>()).collect();
+        let plain: BTreeSet<char> = self.enum_word()
+            .flat_map(|(_, x)| x.mnemonic().chars())
+            .filter(|x| !spelt.contains(x) && !x.is_whitespace())
+            .collect();
+        for x in plain {
+            letters.insert((x.to_string(), x.to_string()), ());
+        }
+        let mut guide: Vec<Letter> = letters.into_keys().map(|(written, mnemonic)| {
+            let mut sound = Vec::new();
+            let mut claimed = BTreeSet::new();
+            for (rule, subs) in m2u.iter().zip(m2u_subs.iter()) {
+                let pairs = literal_pairs(rule, &cat, self.norm()).unwrap_or_default();
+                for (_, ipa) in pairs.into_iter().filter(|(pat, _)| *pat == mnemonic) {
+                    let example: Vec<usize> = self.enum_word()
+                        .filter(|(i, x)| !claimed.contains(i) && subs.iter().any(|sub| sub.pat().is_match(x.mnemonic())))
+                        .map(|(i, _)| i)
+                        .collect();
+                    claimed.extend(example.iter().copied());
+                    sound.push(Sound {
+                        ipa,
+                        context: rule.env().to_owned(),
+                        example: example.into_iter().take(EXAMPLES).collect(),
+                    });
+                }
+            }
+            if sound.iter().all(|x| !x.context.is_empty()) {
+                let example = self.enum_word()
+                    .filter(|(i, x)| !claimed.contains(i) && x.mnemonic().contains(&mnemonic))
+                    .map(|(i, _)| i)
+                    .take(EXAMPLES)
+                    .collect();
+                sound.push(Sound { ipa: mnemonic.clone(), context: String::new(), example });
+            }
+            Letter { written, mnemonic, sound }
+        }).collect();
+        guide.sort_by_key(|x| self.collation_key(&x.written));
+        Ok(guide)
+    }
+
+    pub fn export_guide(&self) -> Result<String, BabelError> {
+        let mut out = format!("# Pronunciation of {}\n\n| Letter | Sound | Context | Examples |\n|---|---|---|---|\n", self.name());
+        for letter in self.pronunciation_guide()? {
+            for sound in letter.sound() {
+                let example: Vec<_> = sound.example().iter().filter_map(|&i| self.word_at(i).ok())
+                    .map(|x| format!("{} [{}]", x.conlang(), x.upa()))
+                    .collect();
+                let context = if sound.context().is_empty() { String::from("elsewhere") } else { format!("/ {}", sound.context()) };
+                writeln!(out, "| {} | [{}] | {} | {} |", letter.written(), sound.ipa(), context, example.join(", ")).unwrap();
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::word::Word;
+
+    #[test]
+    fn test_guide() {
+        let mut lang = Language::new("Test");
+        lang.add_cat('F', "ie").unwrap();
+        lang.add_m2w(None, Replace::new("sh", "š").unwrap()).unwrap();
+        lang.add_m2u(Replace::new("sh", "ʃ").unwrap()).unwrap();
+        lang.add_m2u(Replace::new("k", "tʃ").unwrap().with_env("_F")).unwrap();
+        lang.add_word(Word::shell("shak", 0)).unwrap();
+        lang.add_word(Word::shell("ki", 0)).unwrap();
+        let guide = lang.pronunciation_guide().unwrap();
+        let k = guide.iter().find(|x| x.written() == "k").unwrap();
+        assert_eq!(k.sound().iter().map(|x| (x.ipa(), x.context())).collect::<Vec<_>>(), vec![("tʃ", "_F"), ("k", "")]);
+        assert_eq!(k.sound()[0].example(), &vec![1]);
+        assert_eq!(k.sound()[1].example(), &vec![0]);
+        let sh = guide.iter().find(|x| x.written() == "š").unwrap();
+        assert_eq!((sh.mnemonic(), sh.sound()[0].ipa()), ("sh", "ʃ"));
+    }
+}
@@ -0,0 +1,683 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use super::BabelError;
+use super::language::{Meta, SCA, SoundChange};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Sca2,
+    Lexurgy,
+}
+
+impl FromStr for Format {
+    type Err = BabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sca2" | "sca" => Ok(Format::Sca2),
+            "lexurgy" | "lsc" => Ok(Format::Lexurgy),
+            _ => Err(BabelError::UnknownFormat(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Elem {
+    Text(char),
+    Cat(char),
+    Nonce(String),
+    Opt(Vec<Elem>),
+    Bound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Target,
+    Repl,
+    Pre,
+    Post,
+}
+
+struct Rule {
+    tg: Vec<Elem>,
+    repl: Vec<Elem>,
+    pre: Vec<Elem>,
+    post: Vec<Elem>,
+}
+
+fn is_cat(sca: &SCA, c: char) -> bool {
+    sca.cat().contains_key(&c)
+}
+
+fn parse_native(s: &str, side: Side, sca: &SCA) -> Result<Vec<Elem>, String> {
+    let mut elems = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '^' | '$' if side == Side::Repl => {
+                return Err(format!("back-reference or anchor `{}` in replacement", c));
+            }
+            '^' | '$' if side == Side::Target => return Err(format!("anchor `{}` in target", c)),
+            '^' | '$' => elems.push(Elem::Bound),
+            '[' => {
+                let mut set = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(x @ ('^' | '-' | '\\' | '[')) => {
+                            return Err(format!("`{}` inside a character class", x));
+                        }
+                        Some(x) => set.push(x),
+                        None => return Err(String::from("unclosed character class")),
+                    }
+                }
+                if side == Side::Repl {
+                    return Err(String::from("character class in replacement"));
+                }
+                elems.push(Elem::Nonce(set));
+            }
+            '(' => {
+                if chars.peek() == Some(&'?') {
+                    chars.next();
+                    if chars.next() != Some(':') {
+                        return Err(String::from("special group"));
+                    }
+                }
+                let mut depth = 1;
+                let mut inner = String::new();
+                for x in chars.by_ref() {
+                    match x {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(x);
+                }
+                if depth != 0 {
+                    return Err(String::from("unclosed group"));
+                }
+                let inner = parse_native(&inner, side, sca)?;
+                if chars.peek() == Some(&'?') && side != Side::Repl {
+                    chars.next();
+                    elems.push(Elem::Opt(inner));
+                } else if side == Side::Repl {
+                    return Err(String::from("group in replacement"));
+                } else {
+                    elems.extend(inner);
+                }
+            }
+            '\\' => match chars.next() {
+                Some(x) if x.is_ascii_punctuation() => elems.push(Elem::Text(x)),
+                Some(x) => return Err(format!("escape sequence `\\{}`", x)),
+                None => return Err(String::from("trailing backslash")),
+            },
+            '.' | '*' | '+' | '?' | '|' | '{' | '}' | ')' | ']' => {
+                return Err(format!("regex operator `{}`", c));
+            }
+            c if is_cat(sca, c) => elems.push(Elem::Cat(c)),
+            c => elems.push(Elem::Text(c)),
+        }
+    }
+    Ok(elems)
+}
+
+fn render_native(elems: &[Elem], side: Side) -> String {
+    let mut s = String::new();
+    for elem in elems {
+        match elem {
+            Elem::Text(c) => s.push_str(&regex::escape(&c.to_string())),
+            Elem::Cat(c) => s.push(*c),
+            Elem::Nonce(set) => write!(s, "[{}]", set).unwrap(),
+            Elem::Opt(inner) => write!(s, "(?:{})?", render_native(inner, side)).unwrap(),
+            Elem::Bound => s.push(if side == Side::Pre { '^' } else { '$' }),
+        }
+    }
+    s
+}
+
+fn parse_sca2(s: &str, side: Side, sca: &SCA) -> Result<Vec<Elem>, String> {
+    let mut elems = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => elems.push(Elem::Bound),
+            '[' => {
+                let set: String = chars.by_ref().take_while(|&x| x != ']').collect();
+                elems.push(Elem::Nonce(set));
+            }
+            '(' => {
+                let mut depth = 1;
+                let mut inner = String::new();
+                for x in chars.by_ref() {
+                    match x {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(x);
+                }
+                elems.push(Elem::Opt(parse_sca2(&inner, side, sca)?));
+            }
+            '…' | '²' | '\\' | '%' | '~' | '@' => return Err(format!("SCA² operator `{}`", c)),
+            c if is_cat(sca, c) => elems.push(Elem::Cat(c)),
+            c => elems.push(Elem::Text(c)),
+        }
+    }
+    if side == Side::Repl && elems.iter().any(|x| !matches!(x, Elem::Text(_) | Elem::Cat(_))) {
+        return Err(String::from("non-literal replacement"));
+    }
+    Ok(elems)
+}
+
+fn render_sca2(elems: &[Elem]) -> String {
+    let mut s = String::new();
+    for elem in elems {
+        match elem {
+            Elem::Text(c) | Elem::Cat(c) => s.push(*c),
+            Elem::Nonce(set) => write!(s, "[{}]", set).unwrap(),
+            Elem::Opt(inner) => write!(s, "({})", render_sca2(inner)).unwrap(),
+            Elem::Bound => s.push('#'),
+        }
+    }
+    s
+}
+
+fn lexurgy_class(name: char) -> Option<String> {
+    if name.is_ascii_alphanumeric() {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+fn render_lexurgy(elems: &[Elem]) -> Result<String, String> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    for elem in elems {
+        if let Elem::Text(c) = elem {
+            text.push(*c);
+            continue;
+        }
+        if !text.is_empty() {
+            tokens.push(std::mem::take(&mut text));
+        }
+        match elem {
+            Elem::Cat(c) => {
+                let name = lexurgy_class(*c).ok_or(format!("category name `{}`", c))?;
+                tokens.push(format!("@{}", name));
+            }
+            Elem::Nonce(set) => {
+                let items: Vec<_> = set.chars().map(|x| x.to_string()).collect();
+                tokens.push(format!("{{{}}}", items.join(", ")));
+            }
+            Elem::Opt(inner) => tokens.push(format!("({})?", render_lexurgy(inner)?)),
+            Elem::Bound => tokens.push(String::from("$")),
+            Elem::Text(_) => unreachable!(),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(text);
+    }
+    Ok(tokens.join(" "))
+}
+
+fn parse_lexurgy(s: &str, side: Side, classes: &[(String, char)]) -> Result<Vec<Elem>, String> {
+    let mut elems = Vec::new();
+    let mut chars = s.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => (),
+            '*' if elems.is_empty() && chars.peek().is_none() => (),
+            '$' => elems.push(Elem::Bound),
+            '@' => {
+                let mut name = String::new();
+                while let Some(&x) = chars.peek() {
+                    if x.is_alphanumeric() || x == '-' || x == '_' {
+                        name.push(x);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let (_, short) = classes.iter().find(|(long, _)| *long == name)
+                    .ok_or(format!("unknown class `@{}`", name))?;
+                elems.push(Elem::Cat(*short));
+            }
+            '{' => {
+                let inner: String = chars.by_ref().take_while(|&x| x != '}').collect();
+                let mut set = String::new();
+                for item in inner.split(',').map(|x| x.trim()) {
+                    let mut item_chars = item.chars();
+                    match (item_chars.next(), item_chars.next()) {
+                        (Some(x), None) => set.push(x),
+                        _ => return Err(format!("multi-segment alternative `{}`", item)),
+                    }
+                }
+                elems.push(Elem::Nonce(set));
+            }
+            '(' => {
+                let mut depth = 1;
+                let mut inner = String::new();
+                for x in chars.by_ref() {
+                    match x {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(x);
+                }
+                if chars.next() != Some('?') {
+                    return Err(String::from("repetition"));
+                }
+                elems.push(Elem::Opt(parse_lexurgy(&inner, side, classes)?));
+            }
+            '[' | ']' | '&' | '!' | '|' | '>' | '<' | '+' | '.' | '*' => {
+                return Err(format!("Lexurgy operator `{}`", c));
+            }
+            c => elems.push(Elem::Text(c)),
+        }
+    }
+    if side == Side::Repl && elems.iter().any(|x| !matches!(x, Elem::Text(_) | Elem::Cat(_))) {
+        return Err(String::from("non-literal replacement"));
+    }
+    Ok(elems)
+}
+
+fn split_native(sca: &SCA, sc: &SoundChange) -> Result<Rule, String> {
+    let (pre, post) = sc.env().split_once('_').ok_or("environment without `_`")?;
+    Ok(Rule {
+        tg: parse_native(sc.tg(), Side::Target, sca)?,
+        repl: parse_native(sc.repl(), Side::Repl, sca)?,
+        pre: parse_native(pre, Side::Pre, sca)?,
+        post: parse_native(post, Side::Post, sca)?,
+    })
+}
+
+fn sca2_slashes(line: &str) -> String {
+    line.replacen('→', "/", 1).replacen('>', "/", 1)
+}
+
+// `line` has `→` and `>` already turned into `/`; an exception after a
+// fourth slash is left for the caller to report.
+fn split_sca2(line: &str, sca: &SCA) -> Result<Rule, String> {
+    let parts: Vec<_> = line.split('/').collect();
+    if parts.len() < 3 {
+        return Err(String::from("expected `target/replacement/environment`"));
+    }
+    let env = parts[2].trim();
+    let env = if env.is_empty() { "_" } else { env };
+    let (pre, post) = env.split_once('_').ok_or("environment without `_`")?;
+    Ok(Rule {
+        tg: parse_sca2(parts[0].trim(), Side::Target, sca)?,
+        repl: parse_sca2(parts[1].trim(), Side::Repl, sca)?,
+        pre: parse_sca2(pre, Side::Pre, sca)?,
+        post: parse_sca2(post, Side::Post, sca)?,
+    })
+}
+
+fn join_native(rule: &Rule) -> SoundChange {
+    let env = format!("{}_{}", render_native(&rule.pre, Side::Pre), render_native(&rule.post, Side::Post));
+    SoundChange::new(&render_native(&rule.tg, Side::Target), &render_native(&rule.repl, Side::Repl), &env)
+}
+
+pub fn export(sca: &SCA, format: Format) -> (String, Vec<String>) {
+    match format {
+        Format::Sca2 => export_sca2(sca),
+        Format::Lexurgy => export_lexurgy(sca),
+    }
+}
+
+pub fn import(text: &str, format: Format) -> (SCA, Vec<String>) {
+    match format {
+        Format::Sca2 => import_sca2(text),
+        Format::Lexurgy => import_lexurgy(text),
+    }
+}
+
+// The definition of a category built from others or by set operations,
+// which neither format can express and so gets written out in full.
+fn flattened<'a>(sca: &'a SCA, name: char, content: &str) -> Option<&'a str> {
+    sca.cat().get(&name).map(String::as_str).filter(|x| *x != content)
+}
+
+fn export_sca2(sca: &SCA) -> (String, Vec<String>) {
+    let mut out = String::new();
+    let mut report = Vec::new();
+    let cat = match sca.resolve() {
+        Ok(cat) => cat,
+        Err(err) => return (out, vec![err.to_string()]),
+    };
+    for (name, content) in cat.iter() {
+        if let Some(definition) = flattened(sca, *name, content) {
+            writeln!(out, "* {}={}", name, definition).unwrap();
+            report.push(format!("category {}: `{}` written out as its members", name, definition));
+        }
+        writeln!(out, "{}={}", name, content).unwrap();
+    }
+    for (i, sc) in sca.sc().iter().enumerate() {
+        if !sc.meta().comment().is_empty() {
+            writeln!(out, "* {}", sc.meta().comment()).unwrap();
+        }
+        if sc.optional() {
+            report.push(format!("rule {}: optional rule written as obligatory", i));
+        }
+        // Other tools see a disabled rule as a comment; `import` reads it back.
+        let off = if sc.meta().enabled() { "" } else { "* (off) " };
+        match split_native(sca, sc) {
+            Ok(rule) => writeln!(
+                out,
+                "{}{}/{}/{}_{}",
+                off,
+                render_sca2(&rule.tg),
+                render_sca2(&rule.repl),
+                render_sca2(&rule.pre),
+                render_sca2(&rule.post)
+            ).unwrap(),
+            Err(reason) => {
+                writeln!(out, "* {}/{}/{}", sc.tg(), sc.repl(), sc.env()).unwrap();
+                report.push(format!("rule {}: {}", i, reason));
+            }
+        }
+    }
+    (out, report)
+}
+
+fn export_lexurgy(sca: &SCA) -> (String, Vec<String>) {
+    let mut out = String::new();
+    let mut report = Vec::new();
+    let cat = match sca.resolve() {
+        Ok(cat) => cat,
+        Err(err) => return (out, vec![err.to_string()]),
+    };
+    for (&name, content) in cat.iter() {
+        if let Some(definition) = flattened(sca, name, content) {
+            writeln!(out, "# {} = {}", name, definition).unwrap();
+            report.push(format!("category {}: `{}` written out as its members", name, definition));
+        }
+        let items: Vec<_> = content.chars().map(|x| x.to_string()).collect();
+        match lexurgy_class(name) {
+            Some(class) => writeln!(out, "Class {} {{{}}}", class, items.join(", ")).unwrap(),
+            None => report.push(format!("category {}: name is not a valid Lexurgy class name", name)),
+        }
+    }
+    for (i, sc) in sca.sc().iter().enumerate() {
+        let line = split_native(sca, sc).and_then(|rule| {
+            let tg = render_lexurgy(&rule.tg)?;
+            let repl = render_lexurgy(&rule.repl)?;
+            let pre = render_lexurgy(&rule.pre)?;
+            let post = render_lexurgy(&rule.post)?;
+            let mut line = format!(
+                "{} => {}",
+                if tg.is_empty() { "*" } else { &tg },
+                if repl.is_empty() { "*" } else { &repl }
+            );
+            if !pre.is_empty() || !post.is_empty() {
+                write!(line, " / {} _ {}", pre, post).unwrap();
+            }
+            Ok(line)
+        });
+        let label = sc.meta().label();
+        let name = if !label.is_empty() && label.chars().all(|x| x.is_ascii_alphanumeric() || x == '-') {
+            label.to_owned()
+        } else {
+            format!("rule-{}", i)
+        };
+        writeln!(out).unwrap();
+        if !sc.meta().comment().is_empty() {
+            writeln!(out, "# {}", sc.meta().comment()).unwrap();
+        }
+        if sc.optional() && sc.meta().enabled() {
+            report.push(format!("rule {}: optional rule written as obligatory", i));
+        }
+        match line {
+            Ok(line) if sc.meta().enabled() => writeln!(out, "{}:\n    {}", name, line.trim_end()).unwrap(),
+            Ok(line) => {
+                writeln!(out, "# {}:\n#     {}", name, line.trim_end()).unwrap();
+                report.push(format!("rule {}: disabled, written as a comment", i));
+            }
+            Err(reason) => {
+                writeln!(out, "# {}: {} => {} / {}", name, sc.tg(), sc.repl(), sc.env()).unwrap();
+                report.push(format!("rule {}: {}", i, reason));
+            }
+        }
+    }
+    (out, report)
+}
+
+fn add_rule(sca: &mut SCA, rule: &Rule, meta: Meta, lineno: usize, report: &mut Vec<String>) {
+    let sc = join_native(rule).with_meta(meta);
+    if let Err(err) = sca.add_sc(sc) {
+        report.push(format!("line {}: {}", lineno, err));
+    }
+}
+
+fn import_sca2(text: &str) -> (SCA, Vec<String>) {
+    let mut sca = SCA::new();
+    let mut report = Vec::new();
+    let mut comment = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let lineno = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // A comment starting with `(off)` is only a disabled rule if the rest
+        // reads as one.
+        let (line, enabled) = match line.strip_prefix('*').map(str::trim) {
+            Some(note) => match note.strip_prefix("(off)").map(str::trim) {
+                Some(rule) if !rule.contains(['|', '=']) && split_sca2(&sca2_slashes(rule), &sca).is_ok() => (rule, false),
+                _ => {
+                    comment = note.to_owned();
+                    continue;
+                }
+            },
+            None => (line, true),
+        };
+        let comment = std::mem::take(&mut comment);
+        if line.contains('|') {
+            report.push(format!("line {}: rewrite rules are not supported", lineno));
+            continue;
+        }
+        if let Some((name, content)) = line.split_once('=') {
+            let mut name_chars = name.trim().chars();
+            match (name_chars.next(), name_chars.next()) {
+                (Some(name), None) => if let Err(err) = sca.add_cat(name, content.trim()) {
+                    report.push(format!("line {}: {}", lineno, err));
+                },
+                _ => report.push(format!("line {}: category name must be a single character", lineno)),
+            }
+            continue;
+        }
+        let line = sca2_slashes(line);
+        if let Some(exception) = line.split('/').nth(3).map(str::trim).filter(|x| !x.is_empty()) {
+            report.push(format!("line {}: exception `{}` dropped", lineno, exception));
+        }
+        match split_sca2(&line, &sca) {
+            Ok(rule) => add_rule(&mut sca, &rule, Meta::new("", &comment, enabled), lineno, &mut report),
+            Err(reason) => report.push(format!("line {}: {}", lineno, reason)),
+        }
+    }
+    (sca, report)
+}
+
+fn import_lexurgy(text: &str) -> (SCA, Vec<String>) {
+    let mut sca = SCA::new();
+    let mut report = Vec::new();
+    let mut classes: Vec<(String, char)> = Vec::new();
+    let mut skipping = false;
+    let mut expressions = 0;
+    let mut label = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let lineno = i + 1;
+        let line = match line.split_once('#') {
+            Some((code, _)) => code,
+            None => line,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        let line = line.trim();
+        if !indented {
+            expressions = 0;
+            skipping = false;
+            label.clear();
+            if let Some(decl) = line.strip_prefix("Class ") {
+                let (name, body) = match decl.split_once('{') {
+                    Some((name, body)) => (name.trim(), body.trim_end_matches('}')),
+                    None => {
+                        report.push(format!("line {}: malformed class", lineno));
+                        continue;
+                    }
+                };
+                let mut content = String::new();
+                let mut valid = true;
+                for item in body.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+                    if let Some(other) = item.strip_prefix('@') {
+                        match classes.iter().find(|(long, _)| long == other) {
+                            Some((_, short)) => content.push_str(&sca.cat()[short]),
+                            None => valid = false,
+                        }
+                        continue;
+                    }
+                    let mut item_chars = item.chars();
+                    match (item_chars.next(), item_chars.next()) {
+                        (Some(x), None) => content.push(x),
+                        _ => valid = false,
+                    }
+                }
+                if !valid {
+                    report.push(format!("line {}: class {} has multi-segment or unknown members", lineno, name));
+                    continue;
+                }
+                let mut name_chars = name.chars();
+                let short = match (name_chars.next(), name_chars.next()) {
+                    (Some(x), None) if !is_cat(&sca, x) => x,
+                    _ => match ('A'..='Z').chain('α'..='ω').find(|x| !is_cat(&sca, *x)) {
+                        Some(x) => {
+                            report.push(format!("line {}: class {} renamed to {}", lineno, name, x));
+                            x
+                        }
+                        None => {
+                            report.push(format!("line {}: no category name left for class {}", lineno, name));
+                            continue;
+                        }
+                    },
+                };
+                if let Err(err) = sca.add_cat(short, &content) {
+                    report.push(format!("line {}: {}", lineno, err));
+                    continue;
+                }
+                classes.push((name.to_owned(), short));
+            } else if let Some(header) = line.strip_suffix(':') {
+                let mut words = header.split_whitespace();
+                let name = words.next().unwrap_or("");
+                if matches!(name, "Feature" | "Symbol" | "Diacritic" | "Element" | "Syllables" | "Deromanizer" | "Romanizer") {
+                    report.push(format!("line {}: `{}` block is not supported", lineno, name));
+                    skipping = true;
+                } else if words.next().is_some() {
+                    report.push(format!("line {}: modifiers of rule {} ignored", lineno, name));
+                }
+                label = name.to_owned();
+            } else {
+                report.push(format!("line {}: `{}` is not supported", lineno, line));
+            }
+            continue;
+        }
+        if skipping {
+            continue;
+        }
+        if matches!(line, "then:" | "else:") || line.ends_with(':') {
+            report.push(format!("line {}: `{}` is not supported", lineno, line));
+            continue;
+        }
+        expressions += 1;
+        if expressions == 2 {
+            report.push(format!("line {}: simultaneous expressions are applied one after another", lineno));
+        }
+        let (change, env) = match line.split_once('/') {
+            Some((change, env)) => (change, env),
+            None => (line, "_"),
+        };
+        if env.contains('/') {
+            report.push(format!("line {}: exception dropped", lineno));
+        }
+        let env = env.split('/').next().unwrap_or("");
+        let parsed = change.split_once("=>").or_else(|| change.split_once("->"))
+            .ok_or(String::from("expected `=>`"))
+            .and_then(|(tg, repl)| {
+                let (pre, post) = env.split_once('_').ok_or(String::from("environment without `_`"))?;
+                Ok(Rule {
+                    tg: parse_lexurgy(tg, Side::Target, &classes)?,
+                    repl: parse_lexurgy(repl, Side::Repl, &classes)?,
+                    pre: parse_lexurgy(pre, Side::Pre, &classes)?,
+                    post: parse_lexurgy(post, Side::Post, &classes)?,
+                })
+            });
+        match parsed {
+            Ok(rule) => add_rule(&mut sca, &rule, Meta::new(&label, "", true), lineno, &mut report),
+            Err(reason) => report.push(format!("line {}: {}", lineno, reason)),
+        }
+    }
+    (sca, report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sca2_round_trip() {
+        let text = "V=aeiou\nk/g/V_V\nh//_#\n* (off) p/f/_\n";
+        let (sca, report) = import(text, Format::Sca2);
+        assert!(report.is_empty());
+        assert_eq!(sca.sc()[1].env(), "_$");
+        assert!(!sca.sc()[2].meta().enabled());
+        let (out, report) = export(&sca, Format::Sca2);
+        assert!(report.is_empty());
+        assert_eq!(out, text);
+    }
+
+    #[test]
+    fn test_sca2_flattened() {
+        let mut sca = SCA::new();
+        sca.add_cat('P', "pt").unwrap();
+        sca.add_cat('S', "P-t&pk").unwrap();
+        sca.add_sc(SoundChange::new("S", "b", "_").with_meta(Meta::new("", "", false))).unwrap();
+        let (out, report) = export(&sca, Format::Sca2);
+        assert_eq!(out, "P=pt\n* S=P-t&pk\nS=p\n* (off) S/b/_\n");
+        assert_eq!(report, vec!["category S: `P-t&pk` written out as its members"]);
+        let (sca, report) = import("* (off) the old rule\n* (off) p/f/_\nm/n/_\n", Format::Sca2);
+        assert!(report.is_empty());
+        assert_eq!(sca.sc().len(), 2);
+        assert!(!sca.sc()[0].meta().enabled());
+        assert_eq!(sca.sc()[1].meta().comment(), "");
+        let (sca, _) = import("* (off) the old rule\nm/n/_\n", Format::Sca2);
+        assert_eq!(sca.sc()[0].meta().comment(), "(off) the old rule");
+    }
+
+    #[test]
+    fn test_lexurgy_export() {
+        let mut sca = SCA::new();
+        sca.add_cat('V', "ae").unwrap();
+        sca.add_sc(SoundChange::new("k", "", "V_$")).unwrap();
+        sca.add_sc(SoundChange::new("a+", "e", "_")).unwrap();
+        let (out, report) = export(&sca, Format::Lexurgy);
+        assert!(out.contains("Class V {a, e}"));
+        assert!(out.contains("k => * / @V _ $"));
+        assert_eq!(report.len(), 1);
+        let (sca, report) = import(&out, Format::Lexurgy);
+        assert!(report.is_empty());
+        assert_eq!(sca.sc().len(), 1);
+        assert_eq!(sca.sc()[0].env(), "V_$");
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+use indexmap::IndexMap;
+use unicode_normalization::char::canonical_combining_class;
+use super::BabelError;
+use super::inverse::literal_pairs;
+use super::language::Language;
+use super::orth::{parse_pairs, Normalization};
+
+const PLACE: &[&str] = &[
+    "bilabial", "labiodental", "dental", "alveolar", "postalveolar", "retroflex",
+    "palatal", "velar", "uvular", "pharyngeal", "glottal",
+];
+const MANNER: &[&str] = &[
+    "plosive", "implosive", "affricate", "nasal", "trill", "tap", "lateral-tap",
+    "fricative", "lateral-fricative", "approximant", "lateral-approximant",
+];
+const HEIGHT: &[&str] = &["close", "near-close", "close-mid", "mid", "open-mid", "near-open", "open"];
+const BACKNESS: &[&str] = &["front", "central", "back"];
+// Spacing letters that belong to the sound before them rather than
+// starting one of their own.
+const MODIFIER: &str = "ʰʲʷˠˤⁿˡʼːˑ˞";
+const IGNORED: &str = "ˈˌ.|‖‿/[]-";
+const TIE: &[char] = &['\u{361}', '\u{35c}'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Markup {
+    Markdown,
+    Html,
+}
+
+impl FromStr for Markup {
+    type Err = BabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(Markup::Markdown),
+            "html" => Ok(Markup::Html),
+            _ => Err(BabelError::UnknownFormat(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Markup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Markup::Markdown => write!(f, "Markdown"),
+            Markup::Html => write!(f, "HTML"),
+        }
+    }
+}
+
+// Where a symbol sits in its chart: chart, row, column, and whether it is
+// the voiced (or rounded) member of its cell.
+#[derive(Clone, Copy)]
+struct Feature {
+    vowel: bool,
+    row: usize,
+    column: usize,
+    marked: bool,
+}
+
+fn parse_feature(text: &str) -> Option<Feature> {
+    let mut iter = text.split(':');
+    let (kind, a, b, c) = (iter.next()?, iter.next()?, iter.next()?, iter.next()?);
+    let index = |list: &[&str], x: &str| list.iter().position(|y| *y == x);
+    match kind {
+        "C" => Some(Feature { vowel: false, row: index(MANNER, b)?, column: index(PLACE, a)?, marked: c == "voiced" }),
+        "V" => Some(Feature { vowel: true, row: index(HEIGHT, a)?, column: index(BACKNESS, b)?, marked: c == "rounded" }),
+        _ => None,
+    }
+}
+
+lazy_static! {
+    static ref FEATURE: HashMap<String, Feature> = parse_pairs(
+        "<builtin>/feature.txt", include_str!("../../static/ipa/feature.txt")
+    ).expect("built-in feature table is well-formed").into_iter().map(|(symbol, feature)| {
+        (symbol, parse_feature(&feature).expect("built-in feature table is well-formed"))
+    }).collect();
+}
+
+/// Splits a transcription into sounds: a letter along with its diacritics,
+/// length and secondary articulation, or two letters joined by a tie bar.
+pub fn segment(upa: &str, norm: Normalization) -> Vec<String> {
+    let mut sounds: Vec<String> = Vec::new();
+    for x in norm.apply(upa).chars() {
+        if x.is_whitespace() || IGNORED.contains(x) {
+            continue;
+        }
+        match sounds.last_mut() {
+            Some(last) if canonical_combining_class(x) != 0 || MODIFIER.contains(x) || last.ends_with(TIE) => {
+                last.push(x)
+            }
+            _ => sounds.push(x.to_string()),
+        }
+    }
+    sounds
+}
+
+fn lookup(sound: &str) -> Option<Feature> {
+    if let Some(feature) = FEATURE.get(sound) {
+        return Some(*feature);
+    }
+    let parts: Vec<Feature> = sound.split(TIE).filter_map(|x| {
+        x.chars().next().and_then(|x| FEATURE.get(&x.to_string())).copied()
+    }).collect();
+    match parts.as_slice() {
+        [] => None,
+        [feature] => Some(*feature),
+        [.., last] => Some(Feature {
+            row: MANNER.iter().position(|x| *x == "affricate").unwrap(),
+            ..*last
+        }),
+    }
+}
+
+pub struct Chart {
+    row: Vec<&'static str>,
+    column: Vec<&'static str>,
+    cell: Vec<Vec<Vec<String>>>,
+}
+
+impl Chart {
+    // Only the rows and columns the language uses are kept, in chart order.
+    fn new(rows: &[&'static str], columns: &[&'static str], sounds: &[(String, Feature)]) -> Chart {
+        let used_rows: BTreeSet<usize> = sounds.iter().map(|(_, x)| x.row).collect();
+        let used_columns: BTreeSet<usize> = sounds.iter().map(|(_, x)| x.column).collect();
+        let mut sorted: Vec<&(String, Feature)> = sounds.iter().collect();
+        sorted.sort_by_key(|(sound, x)| (x.marked, sound.clone()));
+        let cell = used_rows.iter().map(|&r| {
+            used_columns.iter().map(|&c| {
+                sorted.iter().filter(|(_, x)| x.row == r && x.column == c).map(|(x, _)| x.clone()).collect()
+            }).collect()
+        }).collect();
+        Chart {
+            row: used_rows.into_iter().map(|x| rows[x]).collect(),
+            column: used_columns.into_iter().map(|x| columns[x]).collect(),
+            cell,
+        }
+    }
+
+    pub fn row(&self) -> &Vec<&'static str> {
+        &self.row
+    }
+
+    pub fn column(&self) -> &Vec<&'static str> {
+        &self.column
+    }
+
+    /// Sounds in row `r`, column `c`; voiceless or unrounded first.
+    pub fn cell(&self, r: usize, c: usize) -> &Vec<String> {
+        &self.cell[r][c]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.row.is_empty()
+    }
+
+    fn write(&self, out: &mut String, markup: Markup) {
+        if self.is_empty() {
+            return;
+        }
+        match markup {
+            Markup::Markdown => {
+                writeln!(out, "| | {} |", self.column.join(" | ")).unwrap();
+                writeln!(out, "|---|{}", "---|".repeat(self.column.len())).unwrap();
+                for (r, row) in self.row.iter().enumerate() {
+                    let cells: Vec<String> = self.cell[r].iter().map(|x| x.join(" ")).collect();
+                    writeln!(out, "| {} | {} |", row, cells.join(" | ")).unwrap();
+                }
+            }
+            Markup::Html => {
+                out.push_str("<table>\n<tr><th></th>");
+                for column in self.column.iter() {
+                    write!(out, "<th>{}</th>", column).unwrap();
+                }
+                out.push_str("</tr>\n");
+                for (r, row) in self.row.iter().enumerate() {
+                    write!(out, "<tr><th>{}</th>", row).unwrap();
+                    for cell in self.cell[r].iter() {
+                        write!(out, "<td>{}</td>", html(&cell.join(" "))).unwrap();
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+        }
+    }
+}
+
+fn html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub struct Inventory {
+    frequency: IndexMap<String, usize>,
+    consonant: Chart,
+    vowel: Chart,
+    other: Vec<String>,
+    unattested: Vec<String>,
+}
+
+impl Inventory {
+    /// Every sound in the lexicon with how many times it occurs, most
+    /// frequent first.
+    pub fn frequency(&self) -> &IndexMap<String, usize> {
+        &self.frequency
+    }
+
+    pub fn consonant(&self) -> &Chart {
+        &self.consonant
+    }
+
+    pub fn vowel(&self) -> &Chart {
+        &self.vowel
+    }
+
+    /// Sounds in the lexicon that fit neither chart.
+    pub fn other(&self) -> &Vec<String> {
+        &self.other
+    }
+
+    /// Sounds `mnemonic_to_upa` can produce that no word uses.
+    pub fn unattested(&self) -> &Vec<String> {
+        &self.unattested
+    }
+}
+
+impl Language {
+    pub fn inventory(&self) -> Inventory {
+        let mut frequency: IndexMap<String, usize> = IndexMap::new();
+        for (_, word) in self.enum_word() {
+            for sound in segment(word.upa(), self.norm()) {
+                *frequency.entry(sound).or_default() += 1;
+            }
+        }
+        frequency.sort_by(|a, x, b, y| y.cmp(x).then_with(|| a.cmp(b)));
+        let mut consonant = Vec::new();
+        let mut vowel = Vec::new();
+        let mut other = Vec::new();
+        for sound in frequency.keys() {
+            match lookup(sound) {
+                Some(feature) if feature.vowel => vowel.push((sound.clone(), feature)),
+                Some(feature) => consonant.push((sound.clone(), feature)),
+                None => other.push(sound.clone()),
+            }
+        }
+        let cat = self.mnemonic_transform().normalized_cat().unwrap_or_default();
+        let unattested: BTreeSet<String> = self.enum_m2u()
+            .filter(|(_, x)| x.meta().enabled())
+            .filter_map(|(_, x)| literal_pairs(x, &cat, self.norm()))
+            .flatten()
+            .flat_map(|(_, repl)| segment(&repl, self.norm()))
+            .filter(|x| !frequency.contains_key(x))
+            .collect();
+        Inventory {
+            consonant: Chart::new(MANNER, PLACE, &consonant),
+            vowel: Chart::new(HEIGHT, BACKNESS, &vowel),
+            frequency,
+            other,
+            unattested: unattested.into_iter().collect(),
+        }
+    }
+
+    pub fn export_inventory(&self, markup: Markup) -> String {
+        let inventory = self.inventory();
+        let mut out = String::new();
+        let heading = |out: &mut String, level: usize, text: &str| match markup {
+            Markup::Markdown => writeln!(out, "{} {}\n", "#".repeat(level), text).unwrap(),
+            Markup::Html => writeln!(out, "<h{0}>{1}</h{0}>", level, html(text)).unwrap(),
+        };
+        heading(&mut out, 1, &format!("Phonology of {}", self.name()));
+        for (title, chart) in [("Consonants", inventory.consonant()), ("Vowels", inventory.vowel())] {
+            if !chart.is_empty() {
+                heading(&mut out, 2, title);
+                chart.write(&mut out, markup);
+                out.push('\n');
+            }
+        }
+        let list = |out: &mut String, items: &[String]| match markup {
+            Markup::Markdown => writeln!(out, "{}\n", items.join(" ")).unwrap(),
+            Markup::Html => writeln!(out, "<p>{}</p>", html(&items.join(" "))).unwrap(),
+        };
+        if !inventory.other().is_empty() {
+            heading(&mut out, 2, "Other sounds");
+            list(&mut out, inventory.other());
+        }
+        if !inventory.unattested().is_empty() {
+            heading(&mut out, 2, "Unattested");
+            list(&mut out, inventory.unattested());
+        }
+        heading(&mut out, 2, "Frequency");
+        let rows: Vec<String> = inventory.frequency().iter().map(|(x, n)| format!("{} {}", x, n)).collect();
+        list(&mut out, &[rows.join(", ")]);
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::language::Replace;
+    use crate::core::word::Word;
+
+    #[test]
+    fn test_inventory() {
+        assert_eq!(segment("ˈt͡ʃaː.pʰi", Normalization::Nfc), vec!["t͡ʃ", "aː", "pʰ", "i"]);
+        let mut lang = Language::new("Test");
+        lang.add_m2u(Replace::new("c", "t͡ʃ").unwrap()).unwrap();
+        lang.add_m2u(Replace::new("q", "ʔ").unwrap()).unwrap();
+        lang.add_word(Word::shell("capi", 0)).unwrap();
+        lang.add_word(Word::shell("pata", 0)).unwrap();
+        let inventory = lang.inventory();
+        assert_eq!(inventory.frequency().get_index(0), Some((&String::from("a"), &3)));
+        assert_eq!(inventory.consonant().row(), &vec!["plosive", "affricate"]);
+        assert_eq!(inventory.consonant().column(), &vec!["bilabial", "alveolar", "postalveolar"]);
+        assert_eq!(inventory.consonant().cell(1, 2), &vec![String::from("t͡ʃ")]);
+        assert_eq!(inventory.vowel().row(), &vec!["close", "open"]);
+        assert_eq!(inventory.unattested(), &vec![String::from("ʔ")]);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+use indexmap::IndexMap;
+use super::BabelError;
+use super::language::{Language, Replace, Substitute};
+use super::orth::{self, Normalization};
+use super::word::Word;
+
+const LIMIT: usize = 256;
+
+fn is_literal(text: &str) -> bool {
+    regex::escape(text) == text
+}
+
+fn expand(text: &str, cat: &IndexMap<char, String>) -> Option<Vec<String>> {
+    let mut forms = vec![String::new()];
+    for x in text.chars() {
+        forms = match cat.get(&x) {
+            Some(content) => forms.iter().flat_map(|form| {
+                orth::clusters(content).into_iter().map(move |unit| format!("{}{}", form, unit))
+            }).take(LIMIT + 1).collect(),
+            None => forms.into_iter().map(|form| format!("{}{}", form, x)).collect(),
+        };
+        if forms.len() > LIMIT {
+            return None;
+        }
+    }
+    Some(forms)
+}
+
+fn pick(text: &str, cat: &IndexMap<char, String>, idx: usize) -> String {
+    text.chars().map(|x| match cat.get(&x) {
+        Some(content) => orth::clusters(content)[idx].to_owned(),
+        None => x.to_string(),
+    }).collect()
+}
+
+// The literal rewrites a rule performs, in the order it performs them, or
+// `None` when the rule can't be undone by swapping strings back.
+pub(super) fn literal_pairs(rule: &Replace, cat: &IndexMap<char, String>, norm: Normalization) -> Option<Vec<(String, String)>> {
+    let pairs = match rule.sound_change() {
+        None => vec![(norm.apply(rule.pat()), norm.apply(rule.repl()))],
+        Some(sc) => {
+            let tg = norm.apply(sc.tg());
+            let repl = norm.apply(sc.repl());
+            if repl.chars().any(|x| cat.contains_key(&x)) {
+                let min_len = tg.chars().chain(repl.chars())
+                    .filter_map(|x| cat.get(&x).map(|s| orth::clusters(s).len()))
+                    .min()?;
+                (0..min_len).map(|idx| (pick(&tg, cat, idx), pick(&repl, cat, idx))).collect()
+            } else {
+                expand(&tg, cat)?.into_iter().map(|x| (x, repl.clone())).collect()
+            }
+        }
+    };
+    let invertible = pairs.iter().all(|(pat, repl)| {
+        is_literal(pat) && !repl.is_empty() && !repl.contains('$')
+    });
+    if invertible { Some(pairs) } else { None }
+}
+
+// Returns whether some readings were dropped for going over `LIMIT`.
+fn unreplace(form: &str, pat: &str, repl: &str, out: &mut BTreeSet<String>) -> bool {
+    let mut results = vec![String::new()];
+    let mut truncated = false;
+    let mut last = 0;
+    for (i, _) in form.match_indices(repl) {
+        let gap = &form[last..i];
+        results = results.into_iter().flat_map(|x| {
+            [format!("{}{}{}", x, gap, repl), format!("{}{}{}", x, gap, pat)]
+        }).collect();
+        truncated |= results.len() > LIMIT;
+        results.truncate(LIMIT);
+        last = i + repl.len();
+    }
+    for x in results {
+        if out.len() >= LIMIT {
+            return true;
+        }
+        out.insert(x + &form[last..]);
+    }
+    truncated
+}
+
+// Everything needed to read written forms back, built once per language.
+struct Reader {
+    pairs: Vec<Vec<(String, String)>>,
+    opaque: Vec<(usize, Vec<Substitute>)>,
+    written: BTreeSet<char>,
+    m2w: Vec<Substitute>,
+}
+
+impl Reader {
+    fn read(&self, written: &str) -> (Vec<String>, bool) {
+        let mut forms = BTreeSet::from([written.to_owned()]);
+        let mut truncated = false;
+        for (pat, repl) in self.pairs.iter().rev().flat_map(|x| x.iter().rev()) {
+            let mut next = BTreeSet::new();
+            for form in forms.iter() {
+                truncated |= unreplace(form, pat, repl, &mut next);
+            }
+            forms = next;
+        }
+        let forms = forms.into_iter().filter(|x| {
+            !x.chars().any(|c| self.written.contains(&c)) && Word::render(x, &self.m2w) == written
+        }).collect();
+        (forms, truncated)
+    }
+
+    // Whether a rule that can't be read back applies to the mnemonic.
+    fn opaque_to(&self, mnemonic: &str) -> bool {
+        self.opaque.iter().any(|(_, subs)| subs.iter().any(|x| x.pat().is_match(mnemonic)))
+    }
+}
+
+pub struct Ambiguity {
+    collision: Vec<(String, Vec<usize>)>,
+    ambiguous: Vec<(usize, Vec<String>)>,
+    unparsed: Vec<usize>,
+    unchecked: Vec<usize>,
+    truncated: Vec<usize>,
+    skipped: Vec<usize>,
+}
+
+impl Ambiguity {
+    /// Written forms shared by words with different mnemonics.
+    pub fn collision(&self) -> &Vec<(String, Vec<usize>)> {
+        &self.collision
+    }
+
+    /// Words whose spelling reads back as more than one mnemonic.
+    pub fn ambiguous(&self) -> &Vec<(usize, Vec<String>)> {
+        &self.ambiguous
+    }
+
+    /// Words whose spelling doesn't read back as their own mnemonic.
+    pub fn unparsed(&self) -> &Vec<usize> {
+        &self.unparsed
+    }
+
+    /// Words a skipped rule applies to, so their reading can't be checked.
+    pub fn unchecked(&self) -> &Vec<usize> {
+        &self.unchecked
+    }
+
+    /// Words with more than `LIMIT` readings; only the first are checked.
+    pub fn truncated(&self) -> &Vec<usize> {
+        &self.truncated
+    }
+
+    /// Rules that aren't plain string rewrites and can't be read back.
+    pub fn skipped(&self) -> &Vec<usize> {
+        &self.skipped
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collision.is_empty() && self.ambiguous.is_empty() && self.unparsed.is_empty()
+            && self.unchecked.is_empty() && self.truncated.is_empty() && self.skipped.is_empty()
+    }
+}
+
+impl Language {
+    // Letters the rules write but no mnemonic uses can't be read as
+    // themselves; any other letter passes straight through.
+    fn reader(&self) -> Result<Reader, BabelError> {
+        let cat = self.mnemonic_transform().normalized_cat().unwrap_or_default();
+        let rules: Vec<_> = self.enum_m2w(None)?.filter(|(_, x)| x.meta().enabled()).collect();
+        let mut pairs = Vec::new();
+        let mut opaque = Vec::new();
+        for (i, rule) in rules.iter() {
+            match literal_pairs(rule, &cat, self.norm()) {
+                Some(x) => pairs.push(x),
+                None => opaque.push((*i, self.make_replace(std::slice::from_ref(*rule))?)),
+            }
+        }
+        let mut alphabet: BTreeSet<char> = self.enum_word().flat_map(|(_, x)| x.mnemonic().chars()).collect();
+        alphabet.extend(rules.iter().flat_map(|(_, x)| self.norm().apply(x.pat()).chars().collect::<Vec<_>>()));
+        let written = pairs.iter().flatten().flat_map(|(_, x)| x.chars()).filter(|x| !alphabet.contains(x)).collect();
+        Ok(Reader { pairs, opaque, written, m2w: self.make_m2w()? })
+    }
+
+    /// Finds the mnemonics that `mnemonic_to_word` spells as `written`. Rules
+    /// that aren't plain string rewrites are skipped on the way back, and every
+    /// candidate is checked by spelling it forwards again.
+    pub fn invert_m2w(&self, written: &str) -> Result<Vec<String>, BabelError> {
+        Ok(self.reader()?.read(written).0)
+    }
+
+    pub fn check_ambiguity(&self) -> Result<Ambiguity, BabelError> {
+        let reader = self.reader()?;
+        let mut by_form: IndexMap<String, Vec<(usize, &str)>> = IndexMap::new();
+        let mut ambiguous = Vec::new();
+        let mut unparsed = Vec::new();
+        let mut unchecked = Vec::new();
+        let mut truncated = Vec::new();
+        for (i, word) in self.enum_word() {
+            let written = word.spell(&reader.m2w);
+            let (parses, cut) = reader.read(&written);
+            if cut {
+                truncated.push(i);
+            }
+            if !parses.iter().any(|x| x == word.mnemonic()) {
+                if reader.opaque_to(word.mnemonic()) {
+                    unchecked.push(i);
+                } else if !cut {
+                    unparsed.push(i);
+                }
+            } else if parses.len() > 1 {
+                ambiguous.push((i, parses));
+            }
+            by_form.entry(written).or_default().push((i, word.mnemonic()));
+        }
+        let collision = by_form.into_iter().filter(|(_, words)| {
+            words.iter().any(|(_, x)| *x != words[0].1)
+        }).map(|(written, words)| (written, words.into_iter().map(|(i, _)| i).collect())).collect();
+        let skipped = reader.opaque.into_iter().map(|(i, _)| i).collect();
+        Ok(Ambiguity { collision, ambiguous, unparsed, unchecked, truncated, skipped })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invert() {
+        let mut lang = Language::new("Test");
+        lang.add_m2w(None, Replace::new("sh", "š").unwrap()).unwrap();
+        lang.add_m2w(None, Replace::new("zh", "š").unwrap()).unwrap();
+        lang.add_m2w(None, Replace::new("aa", "ā").unwrap()).unwrap();
+        assert_eq!(lang.invert_m2w("šā").unwrap(), vec!["shaa", "zhaa"]);
+        assert_eq!(lang.invert_m2w("tak").unwrap(), vec!["tak"]);
+        lang.add_word(Word::shell("sha", 0)).unwrap();
+        lang.add_word(Word::shell("zha", 0)).unwrap();
+        lang.add_word(Word::shell("baa", 0)).unwrap();
+        let report = lang.check_ambiguity().unwrap();
+        assert_eq!(report.collision(), &vec![(String::from("ša"), vec![0, 1])]);
+        assert_eq!(report.ambiguous().len(), 2);
+        assert!(report.unparsed().is_empty());
+        lang.add_m2w(None, Replace::new("a+", "o").unwrap()).unwrap();
+        let report = lang.check_ambiguity().unwrap();
+        assert_eq!(report.skipped(), &vec![3]);
+        assert_eq!(report.unchecked(), &vec![0, 1]);
+        assert!(report.unparsed().is_empty());
+    }
+}
@@ -1,21 +1,102 @@
 use serde::{Deserialize, Serialize};
 // use serde_json::Result as JsonResult;
+use indexmap::IndexMap;
 use regex::{self, Regex};
-use std::collections::HashMap;
 use std::error::Error;
 use super::{Babel, BabelError};
+use super::class::{Category, Class};
+use super::generator::Template;
+use super::orth::{self, Escape, Normalization};
+use super::phonotactics::Phonotactics;
+use super::script::Glyph;
 use super::word::{Word, Coordinate};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Meta {
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    comment: String,
+    #[serde(default = "Meta::default_enabled")]
+    enabled: bool,
+}
+
+impl Meta {
+    pub fn new(label: &str, comment: &str, enabled: bool) -> Meta {
+        Meta { label: label.to_string(), comment: comment.to_string(), enabled }
+    }
+
+    fn default_enabled() -> bool {
+        true
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Meta::new("", "", true)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SoundChange {
     tg: String,
     repl: String,
     env: String,
+    #[serde(default)]
+    optional: bool,
+    #[serde(flatten)]
+    meta: Meta,
 }
 
 impl SoundChange {
     pub fn new(tg: &str, repl: &str, env: &str) -> SoundChange {
-        SoundChange { tg: tg.to_string(), repl: repl.to_string(), env: env.to_string() }
+        SoundChange {
+            tg: tg.to_string(),
+            repl: repl.to_string(),
+            env: env.to_string(),
+            optional: false,
+            meta: Meta::default(),
+        }
+    }
+
+    /// An optional change yields a variant with and one without it; it
+    /// can't apply to only some of its matches in a word.
+    pub fn with_optional(mut self, optional: bool) -> SoundChange {
+        self.optional = optional;
+        self
+    }
+
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    pub fn with_meta(mut self, meta: Meta) -> SoundChange {
+        self.meta = meta;
+        self
+    }
+
+    pub fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    pub fn meta_mut(&mut self) -> &mut Meta {
+        &mut self.meta
     }
 
     pub fn tg(&self) -> &str {
@@ -31,18 +112,85 @@ impl SoundChange {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Stage {
+    name: String,
+    end: usize,
+}
+
+impl Stage {
+    pub fn new(name: &str, end: usize) -> Stage {
+        Stage { name: name.to_string(), end }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SCA {
-    cat: HashMap<char, String>,
+    cat: IndexMap<char, String>,
     sc: Vec<SoundChange>,
+    #[serde(default)]
+    stage: Vec<Stage>,
+    #[serde(skip)]
+    norm: Normalization,
 }
 
 impl SCA {
     pub fn new() -> SCA {
-        SCA { cat: HashMap::new(), sc: Vec::new() }
+        SCA { cat: IndexMap::new(), sc: Vec::new(), stage: Vec::new(), norm: Normalization::default() }
+    }
+
+    pub fn stage(&self) -> &Vec<Stage> {
+        &self.stage
     }
 
-    pub fn cat(&self) -> &HashMap<char, String> {
+    pub fn stage_idx(&self, key: &str) -> Result<usize, BabelError> {
+        match key.parse::<usize>() {
+            Ok(idx) if idx < self.stage.len() => Ok(idx),
+            Ok(_) => Err(BabelError::IndexOutOfRange),
+            Err(_) => self.stage.iter().position(|x| x.name() == key).ok_or(BabelError::UnknownLabel(key.to_owned())),
+        }
+    }
+
+    pub fn stage_start(&self, idx: usize) -> Result<usize, BabelError> {
+        match idx {
+            idx if idx >= self.stage.len() => Err(BabelError::IndexOutOfRange),
+            0 => Ok(0),
+            idx => Ok(self.stage[idx - 1].end()),
+        }
+    }
+
+    pub fn add_stage(&mut self, stage: Stage) -> Result<(), BabelError> {
+        if stage.end() > self.sc.len() {
+            return Err(BabelError::IndexOutOfRange);
+        }
+        let idx = self.stage.partition_point(|x| x.end() <= stage.end());
+        self.stage.insert(idx, stage);
+        Ok(())
+    }
+
+    pub fn rm_stage(&mut self, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(&mut self.stage, idx)
+    }
+
+    pub fn until(&self, end: usize) -> Result<SCA, BabelError> {
+        Ok(SCA {
+            cat: self.cat.clone(),
+            sc: self.sc.get(..end).ok_or(BabelError::IndexOutOfRange)?.to_vec(),
+            stage: self.stage.iter().filter(|x| x.end() <= end).cloned().collect(),
+            norm: self.norm,
+        })
+    }
+
+    pub fn cat(&self) -> &IndexMap<char, String> {
         &self.cat
     }
 
@@ -50,101 +198,262 @@ impl SCA {
         &self.sc
     }
 
-    pub fn add_cat(&mut self, name: char, content: &str) {
-        self.cat.insert(name, content.to_string());
+    pub fn add_cat(&mut self, name: char, content: &str) -> Result<(), BabelError> {
+        let old = self.cat.insert(name, content.to_string());
+        if let Err(err) = self.resolve() {
+            match old {
+                Some(old) => self.cat.insert(name, old),
+                None => self.cat.shift_remove(&name),
+            };
+            return Err(err);
+        }
+        Ok(())
     }
 
     pub fn add_sc(&mut self, sc: SoundChange) -> Result<(), Box<dyn Error>> {
+        Language::check_label(&self.sc, sc.meta().label(), None, SoundChange::meta)?;
         self.compile(&sc)?;
         self.sc.push(sc);
         Ok(())
     }
 
     pub fn alt_sc(&mut self, idx: usize, sc: SoundChange) -> Result<(), Box<dyn Error>> {
+        Language::check_label(&self.sc, sc.meta().label(), Some(idx), SoundChange::meta)?;
         self.compile(&sc)?;
         Language::template_alt(&mut self.sc, idx, sc)?;
         Ok(())
     }
 
     pub fn ins_sc(&mut self, idx: usize, sc: SoundChange) -> Result<(), Box<dyn Error>> {
+        Language::check_label(&self.sc, sc.meta().label(), None, SoundChange::meta)?;
         self.compile(&sc)?;
         Language::template_ins(&mut self.sc, idx, sc)?;
+        for stage in self.stage.iter_mut().filter(|x| x.end > idx) {
+            stage.end += 1;
+        }
         Ok(())
     }
 
+    fn cat_users(&self, name: char) -> Vec<String> {
+        let cats = self.cat.iter().filter(|(&x, content)| x != name && content.contains(name)).map(|(x, _)| format!("category {}", x));
+        let rules = self.sc.iter().enumerate()
+            .filter(|(_, x)| x.tg().contains(name) || x.repl().contains(name) || x.env().contains(name))
+            .map(|(i, _)| format!("rule {}", i));
+        cats.chain(rules).collect()
+    }
+
     pub fn rm_cat(&mut self, name: char) -> Result<String, BabelError> {
-        self.cat.remove(&name).ok_or(BabelError::IndexOutOfRange)
+        if !self.cat.contains_key(&name) {
+            return Err(BabelError::IndexOutOfRange);
+        }
+        let users = self.cat_users(name);
+        if !users.is_empty() {
+            return Err(BabelError::CategoryInUse(name, users.join(", ")));
+        }
+        self.cat.shift_remove(&name).ok_or(BabelError::IndexOutOfRange)
     }
 
     pub fn rm_sc(&mut self, idx: usize) -> Result<(), BabelError> {
-        Language::template_rm(&mut self.sc, idx)
+        Language::template_rm(&mut self.sc, idx)?;
+        for stage in self.stage.iter_mut().filter(|x| x.end > idx) {
+            stage.end -= 1;
+        }
+        Ok(())
     }
 
-    fn compile_unit(&self, sc: &SoundChange) -> Result<Substitute, Box<dyn Error>> {
+    pub fn enable_sc(&mut self, idx: usize, enabled: bool) -> Result<(), BabelError> {
+        let sc = self.sc.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
+        sc.meta_mut().set_enabled(enabled);
+        Ok(())
+    }
+
+    fn resolve_one(&self, name: char, resolved: &mut IndexMap<char, String>, stack: &mut Vec<char>) -> Result<(), BabelError> {
+        if resolved.contains_key(&name) {
+            return Ok(());
+        }
+        if stack.contains(&name) {
+            return Err(BabelError::CyclicCategory(name));
+        }
+        stack.push(name);
+        let mut set: Vec<char> = Vec::new();
+        let mut term: Vec<char> = Vec::new();
+        let mut op = '+';
+        let content = &self.cat[&name];
+        for x in content.chars().chain(std::iter::once('\0')) {
+            if !matches!(x, '-' | '&' | '\0') {
+                if self.cat.contains_key(&x) && x != name {
+                    self.resolve_one(x, resolved, stack)?;
+                    term.extend(resolved[&x].chars());
+                } else {
+                    term.push(x);
+                }
+                continue;
+            }
+            match op {
+                '-' => set.retain(|c| !term.contains(c)),
+                '&' => set.retain(|c| term.contains(c)),
+                _ => set.append(&mut term),
+            }
+            term.clear();
+            op = x;
+        }
+        let mut content = String::new();
+        for x in set {
+            if !content.contains(x) {
+                content.push(x);
+            }
+        }
+        stack.pop();
+        resolved.insert(name, content);
+        Ok(())
+    }
+
+    pub fn resolve(&self) -> Result<IndexMap<char, String>, BabelError> {
+        let mut resolved = IndexMap::new();
+        for &name in self.cat.keys() {
+            self.resolve_one(name, &mut resolved, &mut Vec::new())?;
+        }
+        resolved.sort_by_cached_key(|name, _| self.cat.get_index_of(name));
+        Ok(resolved)
+    }
+
+    pub(super) fn class(content: &str) -> String {
+        let mut units = orth::clusters(content);
+        if units.iter().all(|x| x.chars().count() == 1) {
+            return format!("[{}]", content);
+        }
+        units.sort_by_key(|x| std::cmp::Reverse(x.len()));
+        let units: Vec<_> = units.into_iter().map(regex::escape).collect();
+        format!("(?:{})", units.join("|"))
+    }
+
+    fn compile_unit(&self, sc: &SoundChange, cat: &IndexMap<char, String>) -> Result<Substitute, Box<dyn Error>> {
         let env: Vec<_> = sc.env().split('_').collect();
-        let mut pat = format!(
+        let expand = |s: &str| -> String {
+            s.chars().map(|x| match cat.get(&x) {
+                Some(content) => SCA::class(content),
+                None => x.to_string(),
+            }).collect()
+        };
+        let pat = format!(
             "(?P<pre>{}){}(?P<post>{})",
-            env.get(0).ok_or(BabelError::InvalidSCEnvironment)?,
-            sc.tg(),
-            env.get(1).ok_or(BabelError::InvalidSCEnvironment)?
+            expand(env.first().ok_or(BabelError::InvalidSCEnvironment)?),
+            expand(sc.tg()),
+            expand(env.get(1).ok_or(BabelError::InvalidSCEnvironment)?)
         );
-        for (name, content) in self.cat.iter() {
-            pat = pat.replace(&name.to_string(), &format!("[{}]", content));
-        }
         let repl = format!("${{pre}}{}${{post}}", sc.repl());
         let sub = Substitute::new(&pat, &repl)?;
         Ok(sub)
     }
 
-    pub fn compile(&self, sc: &SoundChange) -> Result<Vec<Substitute>, Box<dyn Error>> {
-        let repl_contains_key = sc.repl().chars().any(|x| self.cat.contains_key(&x));
+    fn compile_with(&self, sc: &SoundChange, cat: &IndexMap<char, String>) -> Result<Vec<Substitute>, Box<dyn Error>> {
+        let sc = &SoundChange::new(
+            &self.norm.apply_pattern(sc.tg()),
+            &self.norm.apply(sc.repl()),
+            &self.norm.apply_pattern(sc.env())
+        ).with_optional(sc.optional());
+        let repl_contains_key = sc.repl().chars().any(|x| cat.contains_key(&x));
         if repl_contains_key {
             let min_tg = sc.tg().chars().filter_map(|x| {
-                self.cat.get(&x).map(|s| utf8_slice::len(s))
+                cat.get(&x).map(|s| orth::clusters(s).len())
             }).min().ok_or(BabelError::InvalidSCTarget)?;
             let min_repl = sc.repl().chars().filter_map(|x| {
-                self.cat.get(&x).map(|s| utf8_slice::len(s))
+                cat.get(&x).map(|s| orth::clusters(s).len())
             }).min().unwrap();
             let min_len = if min_tg < min_repl { min_tg } else { min_repl };
             let mut subset = Vec::new();
             for idx in 0..min_len {
-                let mut tg = sc.tg().to_owned();
-                let mut repl = sc.repl().to_owned();
-                for (name, content) in self.cat.iter() {
-                    tg = tg.replace(&name.to_string(), utf8_slice::slice(content, idx, idx + 1));
-                    repl = repl.replace(&name.to_string(), utf8_slice::slice(content, idx, idx + 1));
-                }
-                let sc = SoundChange::new(&tg, &repl, sc.env());
-                let sub = self.compile_unit(&sc)?;
+                let pick = |s: &str| -> String {
+                    s.chars().map(|x| match cat.get(&x) {
+                        Some(content) => orth::clusters(content)[idx].to_owned(),
+                        None => x.to_string(),
+                    }).collect()
+                };
+                let sc = SoundChange::new(&pick(sc.tg()), &pick(sc.repl()), sc.env());
+                let sub = self.compile_unit(&sc, cat)?;
                 subset.push(sub);
             }
-            Ok(subset)
+            Ok(subset.into_iter().map(|x| x.with_optional(sc.optional())).collect())
         } else {
-            let sub = self.compile_unit(sc)?;
-            Ok(vec![sub])
+            let sub = self.compile_unit(sc, cat)?;
+            Ok(vec![sub.with_optional(sc.optional())])
         }
     }
 
+    // Categories are flattened first and normalized like the rules they feed,
+    // so decomposed input still lines up cluster by cluster.
+    pub(super) fn normalized_cat(&self) -> Result<IndexMap<char, String>, BabelError> {
+        let cat = self.resolve()?;
+        Ok(cat.into_iter().map(|(name, content)| (name, self.norm.apply(&content))).collect())
+    }
+
+    pub fn compile(&self, sc: &SoundChange) -> Result<Vec<Substitute>, Box<dyn Error>> {
+        let cat = self.normalized_cat()?;
+        self.compile_with(sc, &cat)
+    }
+
     pub fn compile_all(&self) -> Result<Vec<Substitute>, Box<dyn Error>> {
+        self.compile_until(self.sc.len())
+    }
+
+    pub fn compile_until(&self, end: usize) -> Result<Vec<Substitute>, Box<dyn Error>> {
+        let cat = self.normalized_cat()?;
         let mut set = Vec::new();
-        for sc in self.sc.iter() {
-            let mut subset = self.compile(sc)?;
+        let sc = self.sc.get(..end).ok_or(BabelError::IndexOutOfRange)?;
+        for sc in sc.iter().filter(|x| x.meta().enabled()) {
+            let mut subset = self.compile_with(sc, &cat)?;
             set.append(&mut subset);
         }
         Ok(set)
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Replace {
     pat: String,
     repl: String,
+    #[serde(default)]
+    env: String,
+    #[serde(flatten)]
+    meta: Meta,
 }
 
 impl Replace {
     pub fn new(pat: &str, repl: &str) -> Result<Replace, regex::Error> {
         Regex::new(pat)?;
-        Ok(Replace { pat: pat.to_string(), repl: repl.to_string() })
+        Ok(Replace { pat: pat.to_string(), repl: repl.to_string(), env: String::new(), meta: Meta::default() })
+    }
+
+    /// With an environment the rule reads like a `SoundChange` and may use the
+    /// language's categories; without one it stays a plain regex.
+    pub fn with_env(mut self, env: &str) -> Replace {
+        self.env = env.to_owned();
+        self
+    }
+
+    pub fn env(&self) -> &str {
+        &self.env
+    }
+
+    pub fn sound_change(&self) -> Option<SoundChange> {
+        if self.env.is_empty() {
+            None
+        } else {
+            Some(SoundChange::new(&self.pat, &self.repl, &self.env))
+        }
+    }
+
+    pub fn with_meta(mut self, meta: Meta) -> Replace {
+        self.meta = meta;
+        self
+    }
+
+    pub fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    pub fn meta_mut(&mut self) -> &mut Meta {
+        &mut self.meta
     }
 
     pub fn pat(&self) -> &str {
@@ -160,18 +469,18 @@ impl Replace {
 pub struct Substitute {
     pat: Regex,
     repl: String,
+    optional: bool,
 }
 
 impl Substitute {
     pub fn new(pat: &str, repl: &str) -> Result<Substitute, regex::Error> {
         let pat = Regex::new(pat)?;
-        Ok(Substitute { pat, repl: repl.to_owned() })
+        Ok(Substitute { pat, repl: repl.to_owned(), optional: false })
     }
-}
 
-impl From<&Replace> for Substitute {
-    fn from(original: &Replace) -> Self {
-        Self { pat: Regex::new(original.pat()).unwrap(), repl: original.repl().to_owned() }
+    pub fn with_optional(mut self, optional: bool) -> Substitute {
+        self.optional = optional;
+        self
     }
 }
 
@@ -183,6 +492,30 @@ impl Substitute {
     pub fn repl(&self) -> &str {
         &self.repl
     }
+
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Orthography {
+    name: String,
+    rule: Vec<Replace>,
+}
+
+impl Orthography {
+    pub fn new(name: &str) -> Orthography {
+        Orthography { name: name.to_string(), rule: Vec::new() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rule(&self) -> &Vec<Replace> {
+        &self.rule
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -193,6 +526,20 @@ pub struct Language {
     mnemonic_to_word: Vec<Replace>,
     mnemonic_to_upa: Vec<Replace>,
     mnemonic_transform: SCA,
+    #[serde(default)]
+    escape: Escape,
+    #[serde(default)]
+    orthography: Vec<Orthography>,
+    #[serde(default)]
+    alphabet: Vec<String>,
+    #[serde(default)]
+    glyph: Vec<Glyph>,
+    #[serde(default)]
+    phonotactics: Phonotactics,
+    #[serde(default)]
+    template: Vec<Template>,
+    #[serde(default)]
+    class: Vec<Class>,
 }
 
 impl Language {
@@ -204,9 +551,28 @@ impl Language {
             mnemonic_to_word: Vec::new(),
             mnemonic_to_upa: Vec::new(),
             mnemonic_transform: SCA::new(),
+            escape: Escape::new(),
+            orthography: Vec::new(),
+            alphabet: Vec::new(),
+            glyph: Vec::new(),
+            phonotactics: Phonotactics::default(),
+            template: Vec::new(),
+            class: Vec::new(),
         }
     }
 
+    pub fn escape(&self) -> &Escape {
+        &self.escape
+    }
+
+    pub fn add_escape(&mut self, key: &str, value: &str) {
+        self.escape.insert(key.to_owned(), value.to_owned());
+    }
+
+    pub fn rm_escape(&mut self, key: &str) -> Result<String, BabelError> {
+        self.escape.remove(key).ok_or(BabelError::UnknownLabel(key.to_owned()))
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -215,6 +581,22 @@ impl Language {
         self.name = name.to_owned();
     }
 
+    pub fn alphabet(&self) -> &Vec<String> {
+        &self.alphabet
+    }
+
+    pub fn set_alphabet(&mut self, alphabet: Vec<String>) {
+        self.alphabet = alphabet;
+    }
+
+    pub fn phonotactics(&self) -> &Phonotactics {
+        &self.phonotactics
+    }
+
+    pub fn set_phonotactics(&mut self, phonotactics: Phonotactics) {
+        self.phonotactics = phonotactics;
+    }
+
     pub fn ancestor(&self) -> Option<usize> {
         self.ancestor
     }
@@ -223,14 +605,69 @@ impl Language {
         &self.mnemonic_transform
     }
 
-    pub fn m2w_at(&self, idx: usize) -> Result<&Replace, BabelError> {
-        Language::template_at(&self.mnemonic_to_word, idx)
+    /// The project's normalization policy, which rules are compiled under.
+    pub fn norm(&self) -> Normalization {
+        self.mnemonic_transform.norm
+    }
+
+    pub(super) fn set_norm(&mut self, norm: Normalization) {
+        self.mnemonic_transform.norm = norm;
+    }
+
+    pub fn set_mnemonic_transform(&mut self, sca: SCA) {
+        self.mnemonic_transform = sca;
+    }
+
+    fn m2w(&self, orth: Option<usize>) -> Result<&Vec<Replace>, BabelError> {
+        match orth {
+            Some(orth) => Ok(&Language::template_at(&self.orthography, orth)?.rule),
+            None => Ok(&self.mnemonic_to_word),
+        }
+    }
+
+    fn m2w_mut(&mut self, orth: Option<usize>) -> Result<&mut Vec<Replace>, BabelError> {
+        match orth {
+            Some(orth) => Ok(&mut self.orthography.get_mut(orth).ok_or(BabelError::IndexOutOfRange)?.rule),
+            None => Ok(&mut self.mnemonic_to_word),
+        }
+    }
+
+    pub fn m2w_at(&self, orth: Option<usize>, idx: usize) -> Result<&Replace, BabelError> {
+        Language::template_at(self.m2w(orth)?, idx)
     }
 
     pub fn m2u_at(&self, idx: usize) -> Result<&Replace, BabelError> {
         Language::template_at(&self.mnemonic_to_upa, idx)
     }
 
+    pub fn glyph_at(&self, idx: usize) -> Result<&Glyph, BabelError> {
+        Language::template_at(&self.glyph, idx)
+    }
+
+    // Units may well be digits, so a unit or name wins over an index.
+    pub fn glyph_idx(&self, key: &str) -> Result<usize, BabelError> {
+        if let Some(idx) = self.glyph.iter().position(|x| x.unit() == key || x.name() == key) {
+            return Ok(idx);
+        }
+        match key.parse() {
+            Ok(idx) if idx < self.glyph.len() => Ok(idx),
+            Ok(_) => Err(BabelError::IndexOutOfRange),
+            Err(_) => Err(BabelError::UnknownLabel(key.to_owned())),
+        }
+    }
+
+    pub fn tpl_at(&self, idx: usize) -> Result<&Template, BabelError> {
+        Language::template_at(&self.template, idx)
+    }
+
+    pub fn class_at(&self, idx: usize) -> Result<&Class, BabelError> {
+        Language::template_at(&self.class, idx)
+    }
+
+    pub fn orth_at(&self, idx: usize) -> Result<&Orthography, BabelError> {
+        Language::template_at(&self.orthography, idx)
+    }
+
     pub fn cat_at(&self, name: char) -> Result<&String, BabelError> {
         self.mnemonic_transform.cat().get(&name).ok_or(BabelError::IndexOutOfRange)
     }
@@ -239,6 +676,25 @@ impl Language {
         Language::template_at(self.mnemonic_transform.sc(), idx)
     }
 
+    pub fn m2w_idx(&self, orth: Option<usize>, key: &str) -> Result<usize, BabelError> {
+        Language::template_idx(self.m2w(orth)?, key, Replace::meta)
+    }
+
+    pub fn orth_idx(&self, key: &str) -> Result<usize, BabelError> {
+        if let Ok(idx) = key.parse() {
+            return if idx < self.orthography.len() { Ok(idx) } else { Err(BabelError::IndexOutOfRange) };
+        }
+        self.orthography.iter().position(|x| x.name == key).ok_or(BabelError::UnknownLabel(key.to_owned()))
+    }
+
+    pub fn m2u_idx(&self, key: &str) -> Result<usize, BabelError> {
+        Language::template_idx(&self.mnemonic_to_upa, key, Replace::meta)
+    }
+
+    pub fn mnt_idx(&self, key: &str) -> Result<usize, BabelError> {
+        Language::template_idx(self.mnemonic_transform.sc(), key, SoundChange::meta)
+    }
+
     pub fn word_at(&self, idx: usize) -> Result<&Word, BabelError> {
         Babel::template_at(&self.vocab, idx)
     }
@@ -247,46 +703,196 @@ impl Language {
         Babel::template_at_mut(&mut self.vocab, idx)
     }
 
-    fn make_m2w(&self) -> Vec<Substitute> {
-        self.mnemonic_to_word.iter().map(|x| Substitute::from(x)).collect()
+    pub fn check_replace(&self, rule: &Replace) -> Result<(), Box<dyn Error>> {
+        if let Some(sc) = rule.sound_change() {
+            self.mnemonic_transform.compile(&sc)?;
+        }
+        Ok(())
+    }
+
+    // Category edits can leave a contextual rule that no longer compiles, so
+    // every caller has to be ready for it.
+    pub(super) fn make_replace(&self, rule: &[Replace]) -> Result<Vec<Substitute>, BabelError> {
+        let mut set = Vec::new();
+        for item in rule.iter().filter(|x| x.meta().enabled()) {
+            match item.sound_change() {
+                Some(sc) => {
+                    let mut subset = self.mnemonic_transform.compile(&sc).map_err(|x| BabelError::BrokenRule(x.to_string()))?;
+                    set.append(&mut subset);
+                }
+                None => {
+                    let pat = self.norm().apply_pattern(item.pat());
+                    let sub = Substitute::new(&pat, &self.norm().apply(item.repl())).map_err(|x| BabelError::BrokenRule(x.to_string()))?;
+                    set.push(sub);
+                }
+            }
+        }
+        Ok(set)
+    }
+
+    pub(super) fn make_m2w(&self) -> Result<Vec<Substitute>, BabelError> {
+        self.make_replace(&self.mnemonic_to_word)
+    }
+
+    pub(super) fn make_orth(&self, orth: usize) -> Result<Vec<Substitute>, BabelError> {
+        let rule = &Language::template_at(&self.orthography, orth)?.rule;
+        self.make_replace(rule)
     }
 
-    fn make_m2u(&self) -> Vec<Substitute> {
-        self.mnemonic_to_upa.iter().map(|x| Substitute::from(x)).collect()
+    /// Spells a word in one of the named orthographies; these renderings are
+    /// not stored, so they always follow the current rules.
+    pub fn spell(&self, word: &Word, orth: usize) -> Result<String, BabelError> {
+        Ok(word.spell(&self.make_orth(orth)?))
     }
 
-    fn make_mnt(&self) -> Vec<Substitute> {
-        self.mnemonic_transform.compile_all().unwrap()
+    pub fn spell_all(&self, word: &Word) -> Result<Vec<(&str, String)>, BabelError> {
+        self.enum_orth().map(|(i, x)| Ok((x.name(), self.spell(word, i)?))).collect()
+    }
+
+    pub(super) fn make_m2u(&self) -> Result<Vec<Substitute>, BabelError> {
+        self.make_replace(&self.mnemonic_to_upa)
+    }
+
+    fn make_mnt(&self, end: usize) -> Result<Vec<Substitute>, BabelError> {
+        self.mnemonic_transform.compile_until(end).map_err(|x| BabelError::BrokenRule(x.to_string()))
+    }
+
+    pub fn stage_end(&self, key: &str) -> Result<usize, BabelError> {
+        let sca = &self.mnemonic_transform;
+        Ok(sca.stage()[sca.stage_idx(key)?].end())
+    }
+
+    pub fn add_stage(&mut self, item: Stage) -> Result<(), BabelError> {
+        self.mnemonic_transform.add_stage(item)
+    }
+
+    pub fn rm_stage(&mut self, idx: usize) -> Result<(), BabelError> {
+        self.mnemonic_transform.rm_stage(idx)
+    }
+
+    pub fn enum_stage(&self) -> impl Iterator<Item = (usize, &Stage)> {
+        Language::template_enum(self.mnemonic_transform.stage())
+    }
+
+    pub fn glimpse(&self, ancestor: &Language, end: usize) -> Result<Vec<(usize, Word)>, BabelError> {
+        let ancestor_idx = self.ancestor.ok_or(BabelError::NoAncestor)?;
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
+        let mnt = self.make_mnt(end)?;
+        let mut glimpse = Vec::new();
+        for (idx, word) in self.enum_word() {
+            let ancestor_coord = word.ancestor();
+            if ancestor_coord.len() == 1 && ancestor_coord[0].lang() == ancestor_idx {
+                let word_ancestor = ancestor.word_at(ancestor_coord[0].word()).map_err(|_| BabelError::GhostWord(idx))?;
+                glimpse.push((idx, word_ancestor.labor(ancestor_coord[0], &mnt, &m2w, &m2u)));
+            }
+        }
+        Ok(glimpse)
     }
 
-    pub fn add_word(&mut self, mut word: Word) {
-        let m2w = self.make_m2w();
-        let m2u = self.make_m2u();
+    pub fn until(&self, name: &str, end: usize) -> Result<Language, BabelError> {
+        Ok(Language {
+            name: name.to_string(),
+            ancestor: None,
+            vocab: Vec::new(),
+            mnemonic_to_word: self.mnemonic_to_word.clone(),
+            mnemonic_to_upa: self.mnemonic_to_upa.clone(),
+            mnemonic_transform: self.mnemonic_transform.until(end)?,
+            escape: self.escape.clone(),
+            orthography: self.orthography.clone(),
+            alphabet: self.alphabet.clone(),
+            glyph: self.glyph.clone(),
+            phonotactics: self.phonotactics.clone(),
+            template: self.template.clone(),
+            class: self.class.clone(),
+        })
+    }
+
+    pub fn add_word(&mut self, mut word: Word) -> Result<(), BabelError> {
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
         word.morph(&m2w, &m2u);
         self.vocab.push(Some(word));
+        Ok(())
+    }
+
+    pub fn add_m2w(&mut self, orth: Option<usize>, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(self.m2w(orth)?, item.meta().label(), None, Replace::meta)?;
+        Language::template_add(self.m2w_mut(orth)?, item);
+        Ok(())
+    }
+
+    pub fn add_glyph(&mut self, item: Glyph) {
+        Language::template_add(&mut self.glyph, item);
     }
 
-    pub fn add_m2w(&mut self, item: Replace) {
-        Language::template_add(&mut self.mnemonic_to_word, item);
+    pub fn add_tpl(&mut self, item: Template) {
+        Language::template_add(&mut self.template, item);
     }
 
-    pub fn add_m2u(&mut self, item: Replace) {
+    pub fn add_class(&mut self, item: Class) {
+        Language::template_add(&mut self.class, item);
+    }
+
+    pub fn add_orth(&mut self, item: Orthography) {
+        Language::template_add(&mut self.orthography, item);
+    }
+
+    pub fn add_m2u(&mut self, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(&self.mnemonic_to_upa, item.meta().label(), None, Replace::meta)?;
         Language::template_add(&mut self.mnemonic_to_upa, item);
+        Ok(())
     }
 
-    pub fn add_cat(&mut self, name: char, content: &str) {
-        self.mnemonic_transform.add_cat(name, content);
+    pub fn add_cat(&mut self, name: char, content: &str) -> Result<(), BabelError> {
+        self.mnemonic_transform.add_cat(name, content)
     }
 
     pub fn add_mnt(&mut self, item: SoundChange) -> Result<(), Box<dyn Error>> {
         self.mnemonic_transform.add_sc(item)
     }
 
-    pub fn alt_m2w(&mut self, idx: usize, item: Replace) -> Result<(), BabelError> {
-        Language::template_alt(&mut self.mnemonic_to_word, idx, item)
+    pub fn enable_m2w(&mut self, orth: Option<usize>, idx: usize, enabled: bool) -> Result<(), BabelError> {
+        let item = self.m2w_mut(orth)?.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
+        item.meta_mut().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn enable_m2u(&mut self, idx: usize, enabled: bool) -> Result<(), BabelError> {
+        let item = self.mnemonic_to_upa.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
+        item.meta_mut().set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn enable_mnt(&mut self, idx: usize, enabled: bool) -> Result<(), BabelError> {
+        self.mnemonic_transform.enable_sc(idx, enabled)
+    }
+
+    pub fn alt_m2w(&mut self, orth: Option<usize>, idx: usize, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(self.m2w(orth)?, item.meta().label(), Some(idx), Replace::meta)?;
+        Language::template_alt(self.m2w_mut(orth)?, idx, item)
+    }
+
+    pub fn alt_glyph(&mut self, idx: usize, item: Glyph) -> Result<(), BabelError> {
+        Language::template_alt(&mut self.glyph, idx, item)
+    }
+
+    pub fn alt_tpl(&mut self, idx: usize, item: Template) -> Result<(), BabelError> {
+        Language::template_alt(&mut self.template, idx, item)
+    }
+
+    pub fn alt_class(&mut self, idx: usize, item: Class) -> Result<(), BabelError> {
+        Language::template_alt(&mut self.class, idx, item)
+    }
+
+    pub fn alt_orth(&mut self, idx: usize, name: &str) -> Result<(), BabelError> {
+        let item = self.orthography.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
+        item.name = name.to_owned();
+        Ok(())
     }
 
     pub fn alt_m2u(&mut self, idx: usize, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(&self.mnemonic_to_upa, item.meta().label(), Some(idx), Replace::meta)?;
         Language::template_alt(&mut self.mnemonic_to_upa, idx, item)
     }
 
@@ -295,41 +901,77 @@ impl Language {
     }
 
     pub fn alt_word(&mut self, idx: usize, mut item: Word) -> Result<(), BabelError> {
-        let m2w = self.make_m2w();
-        let m2u = self.make_m2u();
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
         item.morph(&m2w, &m2u);
         let old_ancestor = self.vocab.get(idx).ok_or(BabelError::IndexOutOfRange)?.as_ref().ok_or(BabelError::InvalidElement)?.ancestor();
         item.set_ancestor(old_ancestor);
         Babel::template_alt(&mut self.vocab, idx, item)
     }
 
-    pub fn drv(&mut self, ancestor_idx: usize, ancestor: &Language) -> Result<(), BabelError> {
-        let m2w = self.make_m2w();
-        let m2u = self.make_m2u();
-        let mnt = self.make_mnt();
+    pub fn drv(&mut self, ancestor_idx: usize, ancestor: &Language, end: Option<usize>) -> Result<(), BabelError> {
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
+        let mnt = self.make_mnt(end.unwrap_or(self.mnemonic_transform.sc().len()))?;
         self.ancestor = Some(ancestor_idx);
         let mut queue: Vec<_> = ancestor.vocab.iter().map(|x| x.as_ref()).collect();
+        // Every ancestral form goes through the sound changes on its own, so
+        // the paradigm keeps whatever the changes made irregular.
+        let labor = |word_ancestor: &Word, coord: Coordinate| {
+            let mut neo_word = word_ancestor.labor(coord, &mnt, &m2w, &m2u);
+            for (cell, mnemonic, _) in ancestor.paradigm_mnemonic(word_ancestor)? {
+                let mut forms = Vec::new();
+                for form in mnemonic.iter().flat_map(|x| Word::evolve(x, &mnt)) {
+                    if !forms.contains(&form) {
+                        forms.push(form);
+                    }
+                }
+                neo_word.set_derived(&cell, forms);
+            }
+            Ok::<_, BabelError>(neo_word)
+        };
+        let mut touched = Vec::new();
         for (idx, word) in self.enum_word_mut() {
             let ancestor_coord = word.ancestor();
             if ancestor_coord.len() == 1 && ancestor_coord[0].lang() == ancestor_idx {
                 let ancestor_coord = ancestor_coord[0];
                 let word_ancestor = queue.get(ancestor_coord.word()).ok_or(BabelError::GhostWord(idx))?.ok_or(BabelError::GhostWord(idx))?;
-                let neo_word = word_ancestor.labor(ancestor_coord, &mnt, &m2w, &m2u);
-                word.fuse(neo_word);
+                word.fuse(labor(word_ancestor, ancestor_coord)?);
                 queue[ancestor_coord.word()] = None;
+                touched.push(idx);
             }
         }
         for (idx, word_option) in queue.iter().enumerate() {
             if let Some(word_ancestor) = *word_option {
                 let ancestor_coord = Coordinate::new(ancestor_idx, idx);
-                self.vocab.push(Some(word_ancestor.labor(ancestor_coord, &mnt, &m2w, &m2u)));
+                touched.push(self.vocab.len());
+                self.vocab.push(Some(labor(word_ancestor, ancestor_coord)?));
             }
         }
+        for idx in touched {
+            self.prune_derived(idx)?;
+        }
         Ok(())
     }
 
-    pub fn enum_m2w(&self) -> impl Iterator<Item = (usize, &Replace)> {
-        Language::template_enum(&self.mnemonic_to_word)
+    pub fn enum_m2w(&self, orth: Option<usize>) -> Result<impl Iterator<Item = (usize, &Replace)>, BabelError> {
+        Ok(Language::template_enum(self.m2w(orth)?))
+    }
+
+    pub fn enum_glyph(&self) -> impl Iterator<Item = (usize, &Glyph)> {
+        Language::template_enum(&self.glyph)
+    }
+
+    pub fn enum_tpl(&self) -> impl Iterator<Item = (usize, &Template)> {
+        Language::template_enum(&self.template)
+    }
+
+    pub fn enum_class(&self) -> impl Iterator<Item = (usize, &Class)> {
+        Language::template_enum(&self.class)
+    }
+
+    pub fn enum_orth(&self) -> impl Iterator<Item = (usize, &Orthography)> {
+        Language::template_enum(&self.orthography)
     }
 
     pub fn enum_m2u(&self) -> impl Iterator<Item = (usize, &Replace)> {
@@ -352,17 +994,50 @@ impl Language {
         Babel::template_enum_mut(&mut self.vocab)
     }
 
+    pub fn split_gloss(&mut self, codes: &[String]) -> (usize, usize) {
+        self.enum_word_mut().map(|(_, x)| x.split_gloss(codes)).fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1))
+    }
+
+    /// Words and classes whose value for the feature `name` would be left
+    /// invalid if that category became `category`, or went away on `None`.
+    pub fn feature_users(&self, name: &str, category: Option<&Category>) -> Vec<String> {
+        let allows = |apply: &[usize], value: &str| category.is_some_and(|x| {
+            x.value().iter().any(|v| v == value) && apply.iter().any(|p| x.apply().contains(p))
+        });
+        let words = self.enum_word()
+            .filter(|(_, x)| x.feature().get(name).is_some_and(|v| !allows(&[x.pos()], v)))
+            .map(|(i, _)| format!("word {}", i));
+        let classes = self.enum_class()
+            .filter(|(_, x)| x.feature().get(name).is_some_and(|v| !allows(x.apply(), v)))
+            .map(|(i, _)| format!("class {}", i));
+        words.chain(classes).collect()
+    }
+
+    pub fn rename_feature(&mut self, old: &str, new: &str) {
+        for (_, word) in self.enum_word_mut() {
+            if let Some(value) = word.feature().get(old).cloned() {
+                word.set_feature(old, "");
+                word.set_feature(new, &value);
+            }
+        }
+        for class in self.class.iter_mut() {
+            class.rename_feature(old, new);
+        }
+    }
+
     pub fn etym_word(&mut self, idx: usize, ancestors: &[Coordinate]) -> Result<(), BabelError> {
         let word = self.vocab.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?.as_mut().ok_or(BabelError::InvalidElement)?;
         word.set_ancestor(ancestors);
         Ok(())
     }
 
-    pub fn ins_m2w(&mut self, idx: usize, item: Replace) -> Result<(), BabelError> {
-        Language::template_ins(&mut self.mnemonic_to_word, idx, item)
+    pub fn ins_m2w(&mut self, orth: Option<usize>, idx: usize, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(self.m2w(orth)?, item.meta().label(), None, Replace::meta)?;
+        Language::template_ins(self.m2w_mut(orth)?, idx, item)
     }
 
     pub fn ins_m2u(&mut self, idx: usize, item: Replace) -> Result<(), BabelError> {
+        Language::check_label(&self.mnemonic_to_upa, item.meta().label(), None, Replace::meta)?;
         Language::template_ins(&mut self.mnemonic_to_upa, idx, item)
     }
 
@@ -370,16 +1045,33 @@ impl Language {
         self.mnemonic_transform.ins_sc(idx, item)
     }
 
-    pub fn revive(&mut self) {
-        let m2w = self.make_m2w();
-        let m2u = self.make_m2u();
+    pub fn revive(&mut self) -> Result<(), BabelError> {
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
         for word in self.vocab.iter_mut().filter_map(|x| x.as_mut()) {
             word.morph(&m2w, &m2u);
         }
+        Ok(())
     }
 
-    pub fn rm_m2w(&mut self, idx: usize) -> Result<(), BabelError> {
-        Language::template_rm(&mut self.mnemonic_to_word, idx)
+    pub fn rm_m2w(&mut self, orth: Option<usize>, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(self.m2w_mut(orth)?, idx)
+    }
+
+    pub fn rm_glyph(&mut self, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(&mut self.glyph, idx)
+    }
+
+    pub fn rm_tpl(&mut self, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(&mut self.template, idx)
+    }
+
+    pub fn rm_class(&mut self, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(&mut self.class, idx)
+    }
+
+    pub fn rm_orth(&mut self, idx: usize) -> Result<(), BabelError> {
+        Language::template_rm(&mut self.orthography, idx)
     }
 
     pub fn rm_m2u(&mut self, idx: usize) -> Result<(), BabelError> {
@@ -399,8 +1091,8 @@ impl Language {
     }
     
     pub fn rst_word(&mut self, idx: usize, mut item: Word) -> Result<(), BabelError> {
-        let m2w = self.make_m2w();
-        let m2u = self.make_m2u();
+        let m2w = self.make_m2w()?;
+        let m2u = self.make_m2u()?;
         item.morph(&m2w, &m2u);
         Babel::template_alt(&mut self.vocab, idx, item)
     }
@@ -409,20 +1101,40 @@ impl Language {
         seq.push(item);
     }
 
-    fn template_alt<T>(seq: &mut Vec<T>, idx: usize, item: T) -> Result<(), BabelError> {
+    fn template_alt<T>(seq: &mut [T], idx: usize, item: T) -> Result<(), BabelError> {
         let old_item = seq.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
         *old_item = item;
         Ok(())
     }
 
-    fn template_at<T>(seq: &Vec<T>, idx: usize) -> Result<&T, BabelError> {
+    fn template_at<T>(seq: &[T], idx: usize) -> Result<&T, BabelError> {
         seq.get(idx).ok_or(BabelError::IndexOutOfRange)
     }
 
-    fn template_enum<T>(seq: &Vec<T>) -> impl Iterator<Item = (usize, &T)> {
+    fn template_enum<T>(seq: &[T]) -> impl Iterator<Item = (usize, &T)> {
         seq.iter().enumerate()
     }
 
+    fn template_idx<T>(seq: &[T], key: &str, meta: impl Fn(&T) -> &Meta) -> Result<usize, BabelError> {
+        match key.parse::<usize>() {
+            Ok(idx) if idx < seq.len() => Ok(idx),
+            Ok(_) => Err(BabelError::IndexOutOfRange),
+            Err(_) => seq.iter().position(|x| meta(x).label() == key).ok_or(BabelError::UnknownLabel(key.to_owned())),
+        }
+    }
+
+    // `skip` is the rule being replaced, which may keep its own label.
+    fn check_label<T>(seq: &[T], label: &str, skip: Option<usize>, meta: impl Fn(&T) -> &Meta) -> Result<(), BabelError> {
+        if label.is_empty() {
+            return Ok(());
+        }
+        let taken = seq.iter().enumerate().any(|(i, x)| Some(i) != skip && meta(x).label() == label);
+        if taken || label.parse::<usize>().is_ok() {
+            return Err(BabelError::InvalidLabel(label.to_owned()));
+        }
+        Ok(())
+    }
+
     fn template_ins<T>(seq: &mut Vec<T>, idx: usize, item: T) -> Result<(), BabelError> {
         if idx > seq.len() {
             return Err(BabelError::IndexOutOfRange);
@@ -465,4 +1177,117 @@ impl Language {
 //     fn is_alive(&self) -> bool {
 //         !self.name.is_empty()
 //     }
-// }
\ No newline at end of file
+// }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nested_category() {
+        let mut sca = SCA::new();
+        sca.add_cat('P', "ptk").unwrap();
+        sca.add_cat('B', "bdg").unwrap();
+        sca.add_cat('S', "PB").unwrap();
+        sca.add_cat('T', "S-pb&tdkg").unwrap();
+        let cat = sca.resolve().unwrap();
+        assert_eq!(cat[&'S'], "ptkbdg");
+        assert_eq!(cat[&'T'], "tkdg");
+        assert_eq!(cat.keys().collect::<String>(), "PBST");
+        sca.add_cat('X', "Y").unwrap();
+        assert!(sca.add_cat('Y', "X").is_err());
+        sca.add_sc(SoundChange::new("a", "e", "P_")).unwrap();
+        let sub = &sca.compile_all().unwrap()[0];
+        assert_eq!(sub.pat().replace_all("pa ba", sub.repl()), "pe ba");
+        assert!(sca.rm_cat('P').is_err());
+        assert!(sca.rm_cat('B').is_err());
+        assert_eq!(sca.rm_cat('X').unwrap(), "Y");
+    }
+
+    #[test]
+    fn test_meta_default() {
+        let sc: SoundChange = serde_json::from_str(r#"{"tg":"a","repl":"e","env":"_"}"#).unwrap();
+        assert!(sc.meta().enabled());
+        let mut sca = SCA::new();
+        sca.add_sc(sc.with_meta(Meta::new("raise", "", false))).unwrap();
+        assert!(sca.compile_all().unwrap().is_empty());
+        assert!(sca.add_sc(SoundChange::new("o", "u", "_").with_meta(Meta::new("raise", "", true))).is_err());
+        assert!(sca.add_sc(SoundChange::new("o", "u", "_").with_meta(Meta::new("1", "", true))).is_err());
+        let mut lang = Language::new("Test");
+        lang.set_mnemonic_transform(sca);
+        assert_eq!(lang.mnt_idx("raise").unwrap(), 0);
+        assert_eq!(lang.mnt_idx("0").unwrap(), 0);
+        assert!(matches!(lang.mnt_idx("1"), Err(BabelError::IndexOutOfRange)));
+        assert!(matches!(lang.m2u_idx("0"), Err(BabelError::IndexOutOfRange)));
+    }
+
+    #[test]
+    fn test_stage_shift() {
+        let mut sca = SCA::new();
+        sca.add_sc(SoundChange::new("a", "e", "_")).unwrap();
+        sca.add_sc(SoundChange::new("e", "i", "_")).unwrap();
+        sca.add_stage(Stage::new("Old", 1)).unwrap();
+        sca.ins_sc(0, SoundChange::new("o", "u", "_")).unwrap();
+        assert_eq!(sca.stage()[0].end(), 2);
+        sca.rm_sc(2).unwrap();
+        assert_eq!(sca.stage()[0].end(), 2);
+        assert_eq!(sca.until(1).unwrap().sc().len(), 1);
+        assert!(sca.until(3).is_err());
+        assert!(sca.compile_until(3).is_err());
+        assert_eq!(sca.stage_start(0).unwrap(), 0);
+        assert!(sca.stage_start(1).is_err());
+        assert!(sca.add_stage(Stage::new("Late", 3)).is_err());
+    }
+
+    #[test]
+    fn test_orthography() {
+        let mut lang = Language::new("Test");
+        lang.add_m2w(None, Replace::new("sh", "ş").unwrap()).unwrap();
+        lang.add_orth(Orthography::new("Cyrillic"));
+        let orth = lang.orth_idx("Cyrillic").unwrap();
+        lang.add_m2w(Some(orth), Replace::new("sh", "ш").unwrap()).unwrap();
+        lang.add_m2w(Some(orth), Replace::new("a", "а").unwrap()).unwrap();
+        lang.add_word(Word::shell("sha", 0)).unwrap();
+        let word = lang.word_at(0).unwrap();
+        assert_eq!(word.conlang(), "şa");
+        assert_eq!(lang.spell_all(word).unwrap(), vec![("Cyrillic", String::from("ша"))]);
+        assert!(lang.add_m2w(Some(1), Replace::new("a", "b").unwrap()).is_err());
+        assert!(lang.orth_idx("1").is_err());
+    }
+
+    #[test]
+    fn test_contextual_replace() {
+        let mut lang = Language::new("Test");
+        lang.add_cat('F', "ie").unwrap();
+        lang.add_m2w(None, Replace::new("k", "qu").unwrap().with_env("_F")).unwrap();
+        lang.add_m2w(None, Replace::new("k", "c").unwrap()).unwrap();
+        lang.add_word(Word::shell("kiko", 0)).unwrap();
+        assert_eq!(lang.word_at(0).unwrap().conlang(), "quico");
+        let bad = Replace::new("k", "c").unwrap().with_env("k");
+        assert!(lang.check_replace(&bad).is_err());
+        lang.add_cat('V', "ao").unwrap();
+        lang.add_cat('W', "ei").unwrap();
+        lang.add_m2u(Replace::new("V", "W").unwrap().with_env("_#")).unwrap();
+        lang.rm_cat('V').unwrap();
+        assert!(lang.add_word(Word::shell("ka", 0)).is_err());
+    }
+
+    #[test]
+    fn test_decomposed_category() {
+        let mut sca = SCA::new();
+        sca.add_cat('V', "a\u{301}e").unwrap();
+        sca.add_sc(SoundChange::new("V", "i", "_")).unwrap();
+        let sub = &sca.compile_all().unwrap()[0];
+        assert_eq!(sub.pat().replace_all("ráe", sub.repl()), "rii");
+        assert_eq!(SCA::class("o\u{301}o"), "(?:o\u{301}|o)");
+    }
+
+    #[test]
+    fn test_decomposed_pattern() {
+        let mut sca = SCA::new();
+        sca.norm = Normalization::Nfd;
+        sca.add_sc(SoundChange::new("[áé]", "i", "_t")).unwrap();
+        let sub = &sca.compile_all().unwrap()[0];
+        let word = Normalization::Nfd.apply("rát rét rat");
+        assert_eq!(sub.pat().replace_all(&word, sub.repl()), "rit rit rat");
+    }
+}
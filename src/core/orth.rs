@@ -1,53 +1,544 @@
-use std::{collections::HashMap, path::PathBuf};
-use regex::{Regex, Captures};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
 use crate::build_path;
+use super::BabelError;
 
-fn get_path(file: &str) -> PathBuf {
+pub mod sampa;
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("acute", include_str!("../../static/orth/acute.txt")),
+    ("bar", include_str!("../../static/orth/bar.txt")),
+    ("breve", include_str!("../../static/orth/breve.txt")),
+    ("cedilla", include_str!("../../static/orth/cedilla.txt")),
+    ("check", include_str!("../../static/orth/check.txt")),
+    ("circle", include_str!("../../static/orth/circle.txt")),
+    ("combination", include_str!("../../static/orth/combination.txt")),
+    ("comma", include_str!("../../static/orth/comma.txt")),
+    ("command", include_str!("../../static/orth/command.txt")),
+    ("ddot", include_str!("../../static/orth/ddot.txt")),
+    ("dot", include_str!("../../static/orth/dot.txt")),
+    ("grave", include_str!("../../static/orth/grave.txt")),
+    ("hat", include_str!("../../static/orth/hat.txt")),
+    ("mark", include_str!("../../static/orth/mark.txt")),
+    ("ogonek", include_str!("../../static/orth/ogonek.txt")),
+    ("tilde", include_str!("../../static/orth/tilde.txt")),
+    ("umlaut", include_str!("../../static/orth/umlaut.txt")),
+];
+
+struct Orth {
+    map: HashMap<String, String>,
+    command: HashMap<String, String>,
+    mark: HashMap<String, char>,
+}
+
+lazy_static! {
+    static ref ORTH: RwLock<Orth> = RwLock::new(
+        init_orth(None).expect("built-in orthography tables are well-formed")
+    );
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    #[default]
+    Nfc,
+    Nfd,
+}
+
+impl Normalization {
+    pub fn apply(&self, string: &str) -> String {
+        match self {
+            Normalization::Nfc => string.nfc().collect(),
+            Normalization::Nfd => string.nfd().collect(),
+        }
+    }
+
+    /// Normalizes a regex so it matches text under the same policy. Under NFD
+    /// a composed letter in a bracket class or before a quantifier would fall
+    /// apart into base and mark, so it is kept together in a group; negated
+    /// classes are left as they are.
+    pub fn apply_pattern(&self, pattern: &str) -> String {
+        if *self == Normalization::Nfc {
+            return self.apply(pattern);
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => {
+                    out.extend(&chars[i..chars.len().min(i + 2)]);
+                    i += 2;
+                }
+                '[' => {
+                    let end = class_end(&chars, i);
+                    out.push_str(&self.apply_class(&chars[i..end]));
+                    i = end;
+                }
+                x => {
+                    let unit = self.apply(&x.to_string());
+                    let quantified = chars.get(i + 1).is_some_and(|x| "*+?{".contains(*x));
+                    if quantified && unit.chars().count() > 1 {
+                        out.push_str(&format!("(?:{})", unit));
+                    } else {
+                        out.push_str(&unit);
+                    }
+                    i += 1;
+                }
+            }
+        }
+        out
+    }
+
+    fn apply_class(&self, class: &[char]) -> String {
+        let text: String = class.iter().collect();
+        let inner = &class[1..class.len() - 1];
+        let split = |x: &char| self.apply(&x.to_string()).chars().count() > 1;
+        if class.last() != Some(&']') || inner.first() == Some(&'^') || inner.contains(&'[') || !inner.iter().any(split) {
+            return self.apply(&text);
+        }
+        let mut units = Vec::new();
+        let mut kept = String::new();
+        let mut j = 0;
+        while j < inner.len() {
+            let len = match inner[j] {
+                '\\' => 2,
+                _ if inner.get(j + 1) == Some(&'-') && j + 2 < inner.len() => 3,
+                x if split(&x) => {
+                    units.push(self.apply(&x.to_string()));
+                    j += 1;
+                    continue;
+                }
+                _ => 1,
+            };
+            kept.extend(&inner[j..inner.len().min(j + len)]);
+            j += len;
+        }
+        units.sort_by_key(|x| std::cmp::Reverse(x.len()));
+        units.dedup();
+        if !kept.is_empty() {
+            units.push(format!("[{}]", self.apply(&kept)));
+        }
+        format!("(?:{})", units.join("|"))
+    }
+}
+
+// The index just past the `]` closing the class opened at `start`.
+fn class_end(chars: &[char], start: usize) -> usize {
+    let mut j = start + 1;
+    if chars.get(j) == Some(&'^') {
+        j += 1;
+    }
+    if chars.get(j) == Some(&']') {
+        j += 1;
+    }
+    let mut depth = 1;
+    while j < chars.len() {
+        match chars[j] {
+            '\\' => j += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return j + 1;
+                }
+            }
+            _ => (),
+        }
+        j += 1;
+    }
+    chars.len()
+}
+
+impl FromStr for Normalization {
+    type Err = BabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nfc" => Ok(Normalization::Nfc),
+            "nfd" => Ok(Normalization::Nfd),
+            _ => Err(BabelError::UnknownFormat(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Normalization {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Normalization::Nfc => write!(f, "NFC"),
+            Normalization::Nfd => write!(f, "NFD"),
+        }
+    }
+}
+
+/// Splits a string into base characters followed by their combining marks.
+pub fn clusters(string: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    for (i, x) in string.char_indices().skip(1) {
+        if canonical_combining_class(x) == 0 {
+            clusters.push(&string[start..i]);
+            start = i;
+        }
+    }
+    if !string.is_empty() {
+        clusters.push(&string[start..]);
+    }
+    clusters
+}
+
+fn get_path(dir: &Path, file: &str) -> PathBuf {
     let filename = format!("{}.txt", file);
-    build_path!("static", "orth", &filename)
+    dir.join(filename)
+}
+
+pub fn default_dir() -> PathBuf {
+    match env::var("NEO_BABEL_ORTH") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => build_path!("static", "orth"),
+    }
+}
+
+fn parse_mark(value: &str) -> Option<char> {
+    u32::from_str_radix(value, 16).ok()
+        .and_then(char::from_u32)
+        .filter(|&x| canonical_combining_class(x) != 0)
+}
+
+pub(super) fn parse_pairs(file: &str, contents: &str) -> Result<Vec<(String, String)>, BabelError> {
+    let mark = file.ends_with("mark.txt");
+    let mut pairs = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let mut iter = line.split_whitespace();
+        match (iter.next(), iter.next(), iter.next()) {
+            (None, _, _) => (),
+            (Some(key), Some(value), None) if !mark || parse_mark(value).is_some() => {
+                pairs.push((key.to_owned(), value.to_owned()))
+            }
+            _ => return Err(BabelError::MalformedOrth(file.to_owned(), i + 1)),
+        }
+    }
+    Ok(pairs)
+}
+
+fn load_table(dir: Option<&Path>, name: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut found = false;
+    let mut pairs = Vec::new();
+    if let Some(&(_, contents)) = BUILTIN.iter().find(|(x, _)| *x == name) {
+        pairs.append(&mut parse_pairs(&format!("<builtin>/{}.txt", name), contents)?);
+        found = true;
+    }
+    if let Some(path) = dir.map(|dir| get_path(dir, name)).filter(|path| path.is_file()) {
+        let contents = fs::read_to_string(&path)?;
+        pairs.append(&mut parse_pairs(&path.display().to_string(), &contents)?);
+        found = true;
+    }
+    if !found {
+        return Err(Box::new(BabelError::MissingOrth(name.to_owned())));
+    }
+    Ok(pairs)
 }
 
-fn init_orth() -> HashMap<String, String> {
-    let contents = fs::read_to_string(get_path("command")).unwrap();
-    let mut commands = HashMap::new();
-    for line in contents.lines() {
-        let pair: Vec<&str> = line.trim().split_whitespace().collect();
-        commands.insert(pair[1], pair[0]);
+fn init_orth(dir: Option<&Path>) -> Result<Orth, Box<dyn Error>> {
+    let mut command = HashMap::new();
+    for (cmd, name) in load_table(dir, "command")? {
+        command.insert(name, cmd);
     }
     let mut map = HashMap::new();
-    for (name, cmd) in commands.into_iter() {
-        let contents = fs::read_to_string(get_path(name)).unwrap();
-        for line in contents.lines() {
-            let pair: Vec<&str> = line.trim().split_whitespace().collect();
-            map.insert(format!("{}{}", cmd, pair[0]), String::from(pair[1]));
+    for (name, cmd) in command.iter() {
+        for (base, repl) in load_table(dir, name)? {
+            map.insert(format!("{}{}", cmd, base), repl);
         }
     }
-    let contents = fs::read_to_string(get_path("combination")).unwrap();
-    for line in contents.lines() {
-        let pair: Vec<&str> = line.trim().split_whitespace().collect();
-        map.insert(String::from(pair[0]), String::from(pair[1]));
+    for (key, repl) in load_table(dir, "combination")? {
+        map.insert(key, repl);
     }
-    map
+    let mut mark = HashMap::new();
+    for (name, code) in load_table(dir, "mark")? {
+        if let (Some(cmd), Some(x)) = (command.get(&name), parse_mark(&code)) {
+            mark.insert(cmd.clone(), x);
+        }
+    }
+    Ok(Orth { map, command, mark })
+}
+
+pub fn reload(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let orth = init_orth(Some(dir))?;
+    *ORTH.write().unwrap() = orth;
+    Ok(())
 }
 
+pub type Escape = BTreeMap<String, String>;
+
+#[cfg(test)]
 pub fn interpret(string: &str) -> String {
-    lazy_static! {
-        static ref ORTH: HashMap<String, String> = init_orth();
-        static ref COMMAND: Regex = Regex::new(r"\\(.{2})").unwrap();
-        static ref COMBINATION: Regex = Regex::new(r"\{(\w+)\}").unwrap();
-        static ref REPLACEMENT: Regex = Regex::new(r"\$(\d+)").unwrap();
+    interpret_with(string, &[])
+}
+
+pub fn interpret_with(string: &str, overlays: &[&Escape]) -> String {
+    let orth = ORTH.read().unwrap();
+    let mut parser = Parser { chars: string.chars().collect(), pos: 0, orth: &orth, overlays };
+    parser.parse_seq(false)
+}
+
+// Inserts a combining mark next to the last base character, beneath any marks
+// already stacked on it, so `\=\'a` reads as a macron with an acute on top.
+fn compose(base: &str, mark: char) -> String {
+    let mut chars: Vec<char> = base.nfd().collect();
+    let idx = chars.iter().rposition(|&x| canonical_combining_class(x) == 0).map_or(0, |i| i + 1);
+    chars.insert(idx, mark);
+    chars.into_iter().collect::<String>().nfc().collect()
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    orth: &'a Orth,
+    overlays: &'a [&'a Escape],
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn lookup(&self, key: &str) -> Option<String> {
+        let overlay = self.overlays.iter().rev().find_map(|x| x.get(key));
+        overlay.or_else(|| self.orth.map.get(key)).cloned()
+    }
+
+    fn parse_seq(&mut self, nested: bool) -> String {
+        let mut string = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => string.push_str(&self.parse_escape()),
+                '{' => string.push_str(&self.parse_group(true)),
+                '}' if nested => return string,
+                '$' if self.peek().is_some_and(|x| x.is_ascii_digit()) => {
+                    let mut digits = String::new();
+                    while let Some(x) = self.peek().filter(|x| x.is_ascii_digit()) {
+                        digits.push(x);
+                        self.pos += 1;
+                    }
+                    string.push_str(&format!("${{{}}}", digits));
+                }
+                _ => string.push(c),
+            }
+        }
+        string
+    }
+
+    fn parse_group(&mut self, combination: bool) -> String {
+        let mut depth = 0;
+        let mut end = None;
+        let mut i = self.pos;
+        while i < self.chars.len() {
+            match self.chars[i] {
+                '\\' => i += 1,
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    end = Some(i);
+                    break;
+                }
+                '}' => depth -= 1,
+                _ => (),
+            }
+            i += 1;
+        }
+        let end = match end {
+            Some(end) => end,
+            None => return String::from("{"),
+        };
+        let raw: String = self.chars[self.pos..end].iter().collect();
+        if combination && !raw.is_empty() && raw.chars().all(|x| x.is_alphanumeric() || x == '_') {
+            if let Some(repl) = self.lookup(&raw) {
+                self.pos = end + 1;
+                return repl;
+            }
+        }
+        self.parse_seq(true)
+    }
+
+    fn parse_item(&mut self) -> String {
+        match self.peek() {
+            None => String::new(),
+            Some(c) => {
+                self.pos += 1;
+                match c {
+                    '\\' => self.parse_escape(),
+                    '{' => self.parse_group(false),
+                    _ => c.to_string(),
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> String {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return String::from("\\"),
+        };
+        self.pos += 1;
+        if matches!(c, '\\' | '{' | '}' | '$') {
+            return c.to_string();
+        }
+        if !c.is_alphabetic() {
+            return if self.orth.mark.contains_key(&c.to_string()) {
+                self.parse_accent(&c.to_string())
+            } else {
+                self.parse_pair(c)
+            };
+        }
+        let mut run = vec![c];
+        while let Some(x) = self.peek().filter(|x| x.is_alphabetic()) {
+            run.push(x);
+            self.pos += 1;
+        }
+        let name: String = run.iter().collect();
+        // A named command needs its argument right after it, so `\hat` alone
+        // still reads as `\ha` followed by `t`.
+        let delimited = matches!(self.peek(), Some('{' | '\\'));
+        if let Some(cmd) = self.orth.command.get(&name).filter(|_| delimited || run.len() == 1).cloned() {
+            return self.parse_accent(&cmd);
+        }
+        if run.len() == 1 {
+            return if self.orth.mark.contains_key(&name) {
+                self.parse_accent(&name)
+            } else {
+                self.parse_pair(c)
+            };
+        }
+        let rest: String = run[2..].iter().collect();
+        let key: String = run[..2].iter().collect();
+        match self.lookup(&key) {
+            Some(repl) => repl + &rest,
+            None if self.orth.mark.contains_key(&c.to_string()) => {
+                self.accent(&c.to_string(), &run[1].to_string()) + &rest
+            }
+            None => name,
+        }
     }
-    let repl_closure = |caps: &Captures| {
-        match ORTH.get(&caps[1]) {
-            Some(repl) => repl.clone(),
-            None => String::from(&caps[1])
+
+    fn parse_pair(&mut self, c: char) -> String {
+        if let Some(next) = self.peek() {
+            if let Some(repl) = self.lookup(&format!("{}{}", c, next)) {
+                self.pos += 1;
+                return repl;
+            }
         }
+        c.to_string()
+    }
+
+    fn parse_accent(&mut self, cmd: &str) -> String {
+        let base = self.parse_item();
+        self.accent(cmd, &base)
+    }
+
+    fn accent(&self, cmd: &str, base: &str) -> String {
+        if base.chars().count() == 1 {
+            if let Some(repl) = self.lookup(&format!("{}{}", cmd, base)) {
+                return repl;
+            }
+        }
+        match self.orth.mark.get(cmd) {
+            Some(&mark) => compose(base, mark),
+            None => format!("{}{}", cmd, base),
+        }
+    }
+}
+
+fn escape_form(key: &str) -> Option<String> {
+    if key.chars().count() == 2 {
+        Some(format!("\\{}", key))
+    } else if !key.is_empty() && key.chars().all(|x| x.is_alphanumeric() || x == '_') {
+        Some(format!("{{{}}}", key))
+    } else {
+        None
+    }
+}
+
+fn express_cluster(cluster: &[char], reverse: &HashMap<&str, String>, marks: &HashMap<char, &str>) -> String {
+    let literal = |x: char| match x {
+        '\\' | '{' | '}' => format!("\\{}", x),
+        _ => x.to_string(),
     };
-    let string = COMBINATION.replace_all(string, repl_closure);
-    let string = COMMAND.replace_all(&string, repl_closure);
-    let string = REPLACEMENT.replace_all(&string, "$${$1}");
-    string.into_owned()
+    let chars: Vec<char> = cluster.iter().collect::<String>().nfd().collect();
+    if chars.len() > 1 && chars[1..].iter().all(|x| marks.contains_key(x)) {
+        let base = chars[0].to_string();
+        let mut expressed = String::new();
+        for x in chars[1..].iter() {
+            expressed.push('\\');
+            expressed.push_str(marks[x]);
+        }
+        let base = reverse.get(base.as_str()).cloned().unwrap_or_else(|| literal(chars[0]));
+        if expressed.ends_with(char::is_alphabetic) && !base.starts_with('\\') {
+            expressed.push_str(&format!("{{{}}}", base));
+        } else {
+            expressed.push_str(&base);
+        }
+        return expressed;
+    }
+    cluster.iter().map(|&x| literal(x)).collect()
+}
+
+pub fn express_with(string: &str, overlays: &[&Escape]) -> String {
+    lazy_static! {
+        static ref PLACEHOLDER: Regex = Regex::new(r"\$\{(\d+)\}").unwrap();
+    }
+    let orth = ORTH.read().unwrap();
+    let mut effective = orth.map.clone();
+    for overlay in overlays {
+        effective.extend(overlay.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    let mut reverse: HashMap<&str, String> = HashMap::new();
+    for (key, value) in effective.iter() {
+        if let Some(form) = escape_form(key) {
+            match reverse.get(value.as_str()) {
+                Some(old) if (old.len(), old) <= (form.len(), &form) => (),
+                _ => {
+                    reverse.insert(value, form);
+                }
+            }
+        }
+    }
+    let marks: HashMap<char, &str> = orth.mark.iter().map(|(k, &v)| (v, k.as_str())).collect();
+    let max_len = reverse.keys().map(|x| x.chars().count()).max().unwrap_or(0);
+    let string = PLACEHOLDER.replace_all(string, "$$$1");
+    let chars: Vec<char> = string.chars().collect();
+    let mut expressed = String::new();
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        for len in (1..=max_len.min(chars.len() - i)).rev() {
+            let piece: String = chars[i..i + len].iter().collect();
+            let stacked = chars.get(i + len).is_some_and(|&x| canonical_combining_class(x) != 0);
+            if let Some(form) = reverse.get(piece.as_str()).filter(|_| !stacked) {
+                expressed.push_str(form);
+                i += len;
+                // `\ha` followed by `t` would otherwise read back as `\hat`
+                let run: String = chars[i..].iter().take_while(|x| x.is_alphabetic()).collect();
+                if form.starts_with('\\') && form.ends_with(char::is_alphabetic) && !run.is_empty()
+                    && orth.command.contains_key(&format!("{}{}", &form[1..], run)) {
+                    expressed.push_str("{}");
+                }
+                continue 'outer;
+            }
+        }
+        let len = 1 + chars[i + 1..].iter().take_while(|&&x| canonical_combining_class(x) != 0).count();
+        expressed.push_str(&express_cluster(&chars[i..i + len], &reverse, &marks));
+        i += len;
+    }
+    expressed
 }
 
 #[cfg(test)]
@@ -58,4 +549,66 @@ mod test {
         let string = r"se\~n{o}rita";
         assert_eq!(interpret(string), String::from("señørita"));
     }
+
+    #[test]
+    fn test_overlay() {
+        let mut global = Escape::new();
+        global.insert(String::from("th"), String::from("θ"));
+        let mut local = Escape::new();
+        local.insert(String::from("th"), String::from("þ"));
+        assert_eq!(interpret_with(r"\th{th}", &[&global]), String::from("θθ"));
+        assert_eq!(interpret_with(r"\th{o}", &[&global, &local]), String::from("þø"));
+    }
+
+    #[test]
+    fn test_express() {
+        assert_eq!(express_with("señørita", &[]), String::from(r"se\~n{o}rita"));
+        assert_eq!(express_with("${1}þ", &[]), String::from(r"$1\th"));
+        let mut local = Escape::new();
+        local.insert(String::from("th"), String::from("þ"));
+        assert_eq!(interpret_with(&express_with("þæ", &[&local]), &[&local]), String::from("þæ"));
+    }
+
+    #[test]
+    fn test_stack() {
+        assert_eq!(interpret(r"\acute{a}\v{c}\vcat"), String::from("áččat"));
+        assert_eq!(interpret(r"\=\'a"), String::from("\u{101}\u{301}"));
+        assert_eq!(interpret(r"\hat"), interpret(r"\ha{}t"));
+        assert_eq!(interpret(r"\hat{a}"), String::from("â"));
+        assert_eq!(interpret(r"\'x\\\{\}"), String::from("x\u{301}\\{}"));
+        assert_eq!(express_with("\u{101}\u{301}", &[]), String::from(r"\=\'a"));
+        for string in ["a\\b{c}", "x\u{301}\u{30c}", "ő t\u{30b}", "ǘ"] {
+            assert_eq!(interpret(&express_with(string, &[])), String::from(string));
+        }
+    }
+
+    #[test]
+    fn test_clusters() {
+        let string = Normalization::Nfd.apply("ṓa");
+        assert_eq!(clusters(&string), vec!["o\u{304}\u{301}", "a"]);
+        assert_eq!(Normalization::Nfc.apply(&string), String::from("ṓa"));
+        assert!(clusters("").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_pattern() {
+        let nfd = Normalization::Nfd;
+        assert_eq!(nfd.apply_pattern("[áe]t"), "(?:a\u{301}|[e])t");
+        assert_eq!(nfd.apply_pattern("[a-zé]"), "(?:e\u{301}|[a-z])");
+        assert_eq!(nfd.apply_pattern("á+[^é]"), "(?:a\u{301})+[^e\u{301}]");
+        assert_eq!(Normalization::Nfc.apply_pattern("a\u{301}+"), "á+");
+        let re = Regex::new(&nfd.apply_pattern("^k[áé]+$")).unwrap();
+        assert!(re.is_match(&nfd.apply("kéá")));
+        assert!(!re.is_match(&nfd.apply("ka")));
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(init_orth(None).is_ok());
+        match parse_pairs("acute.txt", "A Á\n\na á x\n") {
+            Err(BabelError::MalformedOrth(file, line)) => assert_eq!((file.as_str(), line), ("acute.txt", 3)),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse_pairs("mark.txt", "acute 0301\ngrave 0041\n").is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+use super::parse_pairs;
+use crate::core::BabelError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notation {
+    XSampa,
+    Kirshenbaum,
+}
+
+impl FromStr for Notation {
+    type Err = BabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "xs" | "xsampa" | "x-sampa" => Ok(Notation::XSampa),
+            "kb" | "kirshenbaum" => Ok(Notation::Kirshenbaum),
+            _ => Err(BabelError::UnknownFormat(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Notation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Notation::XSampa => write!(f, "X-SAMPA"),
+            Notation::Kirshenbaum => write!(f, "Kirshenbaum"),
+        }
+    }
+}
+
+struct Table {
+    forward: HashMap<String, String>,
+    reverse: HashMap<String, String>,
+}
+
+impl Table {
+    fn new(file: &str, contents: &str) -> Table {
+        let pairs = parse_pairs(file, contents).expect("built-in notation tables are well-formed");
+        let mut forward = HashMap::new();
+        let mut reverse = HashMap::new();
+        for (ascii, ipa) in pairs {
+            let ipa: String = ipa.nfd().collect();
+            // the first spelling listed for a symbol is the one we write back
+            reverse.entry(ipa.clone()).or_insert_with(|| ascii.clone());
+            forward.insert(ascii, ipa);
+        }
+        Table { forward, reverse }
+    }
+}
+
+lazy_static! {
+    static ref XSAMPA: Table = Table::new(
+        "<builtin>/xsampa.txt", include_str!("../../../static/notation/xsampa.txt")
+    );
+    static ref KIRSHENBAUM: Table = Table::new(
+        "<builtin>/kirshenbaum.txt", include_str!("../../../static/notation/kirshenbaum.txt")
+    );
+}
+
+fn table(notation: Notation) -> &'static Table {
+    match notation {
+        Notation::XSampa => &XSAMPA,
+        Notation::Kirshenbaum => &KIRSHENBAUM,
+    }
+}
+
+fn longest_match(chars: &[char], map: &HashMap<String, String>, protect: &str) -> String {
+    let max_len = map.keys().map(|x| x.chars().count()).max().unwrap_or(0);
+    let mut converted = String::new();
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        if protect.contains(chars[i]) {
+            converted.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        for len in (1..=max_len.min(chars.len() - i)).rev() {
+            let piece: String = chars[i..i + len].iter().collect();
+            if let Some(repl) = map.get(&piece) {
+                converted.push_str(repl);
+                i += len;
+                continue 'outer;
+            }
+        }
+        converted.push(chars[i]);
+        i += 1;
+    }
+    converted
+}
+
+/// Converts ASCII transcription into IPA, leaving the characters in `protect`
+/// (category names, the `_` of an environment) untouched.
+pub fn from_ascii(string: &str, notation: Notation, protect: &str) -> String {
+    let chars: Vec<char> = string.chars().collect();
+    longest_match(&chars, &table(notation).forward, protect).nfc().collect()
+}
+
+/// Like `from_ascii` for a regex. Every symbol is phonetic, so regex syntax
+/// the notations also use for sounds is typed after `;`, as in `;.`, `;\\d`
+/// or `;{2}`; `(?…` openers and anything in `protect` are kept as they are.
+pub fn from_ascii_regex(string: &str, notation: Notation, protect: &str) -> String {
+    let chars: Vec<char> = string.chars().collect();
+    let mut converted = String::new();
+    let mut literal: Vec<char> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let (skip, syntax) = match rest {
+            [';', '\\', ..] => (1, rest.len().min(3) - 1),
+            [';', '{', ..] => (1, rest.iter().position(|&x| x == '}').unwrap_or(rest.len() - 1)),
+            [';', _, ..] => (1, 1),
+            ['(', '?', ..] => (0, rest.iter().position(|&x| matches!(x, ':' | '>' | ')')).map_or(2, |end| end + 1)),
+            [x, ..] if protect.contains(*x) => (0, 1),
+            _ => (0, 0),
+        };
+        if syntax == 0 {
+            literal.push(rest[0]);
+            i += 1;
+            continue;
+        }
+        converted.push_str(&regex::escape(&longest_match(&literal, &table(notation).forward, "")));
+        literal.clear();
+        converted.extend(&rest[skip..skip + syntax]);
+        i += skip + syntax;
+    }
+    converted.push_str(&regex::escape(&longest_match(&literal, &table(notation).forward, "")));
+    converted.nfc().collect()
+}
+
+pub fn to_ascii(string: &str, notation: Notation) -> String {
+    let chars: Vec<char> = string.nfd().collect();
+    longest_match(&chars, &table(notation).reverse, "")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_notation() {
+        assert_eq!(from_ascii(r#""SaN@r\i"#, Notation::XSampa, ""), String::from("ˈʃaŋəɹi"));
+        assert_eq!(from_ascii("t_hA~", Notation::XSampa, ""), String::from("tʰɑ̃"));
+        assert_eq!(from_ascii("VSV_h", Notation::XSampa, "V_"), String::from("VʃV_h"));
+        assert_eq!(from_ascii_regex(r";^S;{2}(?:a;|@).t_$", Notation::XSampa, "$()[]_"), String::from(r"^ʃ{2}(?:a|ə)\.t_$"));
+        assert_eq!(from_ascii_regex("?a;?", Notation::XSampa, "$()[]_"), String::from("ʔa?"));
+        assert_eq!(from_ascii_regex(r"r\_[ij]", Notation::XSampa, "$()[]_"), String::from("ɹ_[ij]"));
+        assert_eq!(from_ascii_regex(r"r\;\d", Notation::XSampa, ""), String::from(r"ɹ\d"));
+        assert_eq!(from_ascii_regex("?;*", Notation::Kirshenbaum, ""), String::from("ʔ*"));
+        assert_eq!(to_ascii("ʔãɣ", Notation::XSampa), String::from("?a~G"));
+        assert_eq!(from_ascii("S<h>iN", Notation::Kirshenbaum, ""), String::from("ʃʰiŋ"));
+        assert_eq!(to_ascii("ʃʰiŋ", Notation::Kirshenbaum), String::from("S<h>iN"));
+    }
+}
@@ -0,0 +1,309 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use super::BabelError;
+use super::language::{Language, SCA};
+use super::orth::Normalization;
+
+/// Constraints on the shape of a mnemonic. Patterns use the language's
+/// categories like sound changes do; `#` at either end of a forbidden
+/// sequence anchors it to the word boundary, and a syllable shape marks
+/// optional parts with parentheses, e.g. `(C)(C)V(C)`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Phonotactics {
+    #[serde(default = "Phonotactics::default_vowel")]
+    vowel: char,
+    #[serde(default)]
+    shape: String,
+    #[serde(default)]
+    onset: Vec<String>,
+    #[serde(default)]
+    coda: Vec<String>,
+    #[serde(default)]
+    cluster: Vec<String>,
+    #[serde(default)]
+    forbid: Vec<String>,
+    #[serde(default)]
+    strict: bool,
+}
+
+impl Default for Phonotactics {
+    fn default() -> Self {
+        Phonotactics {
+            vowel: Phonotactics::default_vowel(),
+            shape: String::new(),
+            onset: Vec::new(),
+            coda: Vec::new(),
+            cluster: Vec::new(),
+            forbid: Vec::new(),
+            strict: false,
+        }
+    }
+}
+
+impl Phonotactics {
+    pub fn new(vowel: char, shape: &str) -> Phonotactics {
+        Phonotactics { vowel, shape: shape.to_owned(), ..Phonotactics::default() }
+    }
+
+    fn default_vowel() -> char {
+        'V'
+    }
+
+    pub fn with_onset(mut self, onset: Vec<String>) -> Phonotactics {
+        self.onset = onset;
+        self
+    }
+
+    pub fn with_coda(mut self, coda: Vec<String>) -> Phonotactics {
+        self.coda = coda;
+        self
+    }
+
+    pub fn with_cluster(mut self, cluster: Vec<String>) -> Phonotactics {
+        self.cluster = cluster;
+        self
+    }
+
+    pub fn with_forbid(mut self, forbid: Vec<String>) -> Phonotactics {
+        self.forbid = forbid;
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Phonotactics {
+        self.strict = strict;
+        self
+    }
+
+    pub fn vowel(&self) -> char {
+        self.vowel
+    }
+
+    pub fn shape(&self) -> &str {
+        &self.shape
+    }
+
+    pub fn onset(&self) -> &Vec<String> {
+        &self.onset
+    }
+
+    pub fn coda(&self) -> &Vec<String> {
+        &self.coda
+    }
+
+    pub fn cluster(&self) -> &Vec<String> {
+        &self.cluster
+    }
+
+    pub fn forbid(&self) -> &Vec<String> {
+        &self.forbid
+    }
+
+    /// Whether violating words are rejected instead of only flagged.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    Shape,
+    Onset(String),
+    Coda(String),
+    Cluster(String),
+    Forbidden(String),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Shape => write!(f, "doesn't split into syllables of the allowed shape"),
+            Violation::Onset(x) => write!(f, "onset not allowed: {}", x),
+            Violation::Coda(x) => write!(f, "coda not allowed: {}", x),
+            Violation::Cluster(x) => write!(f, "cluster not allowed: {}", x),
+            Violation::Forbidden(x) => write!(f, "forbidden sequence: {}", x),
+        }
+    }
+}
+
+fn expand(pattern: &str, cat: &IndexMap<char, String>, norm: Normalization) -> String {
+    norm.apply_pattern(pattern).chars().map(|x| match cat.get(&x) {
+        Some(content) => SCA::class(content),
+        None => x.to_string(),
+    }).collect()
+}
+
+// User parentheses become optional groups before categories are expanded,
+// so the groups a category turns into are left alone.
+fn syllable(shape: &str, cat: &IndexMap<char, String>, norm: Normalization) -> String {
+    norm.apply(shape).chars().map(|x| match x {
+        '(' => String::from("(?:"),
+        ')' => String::from(")?"),
+        x => match cat.get(&x) {
+            Some(content) => SCA::class(content),
+            None => x.to_string(),
+        },
+    }).collect()
+}
+
+fn anchored(patterns: &[String], cat: &IndexMap<char, String>, norm: Normalization) -> Result<Vec<Regex>, regex::Error> {
+    patterns.iter().map(|x| Regex::new(&format!("^(?:{})$", expand(x, cat, norm)))).collect()
+}
+
+struct Compiled {
+    vowel: Option<Regex>,
+    shape: Option<Regex>,
+    onset: Vec<Regex>,
+    coda: Vec<Regex>,
+    cluster: Vec<Regex>,
+    forbid: Vec<(String, Regex)>,
+}
+
+impl Compiled {
+    fn new(tactics: &Phonotactics, cat: &IndexMap<char, String>, norm: Normalization) -> Result<Compiled, regex::Error> {
+        let vowel = match cat.get(&tactics.vowel) {
+            Some(content) => Some(Regex::new(&SCA::class(content))?),
+            None => None,
+        };
+        let shape = if tactics.shape.is_empty() {
+            None
+        } else {
+            Some(Regex::new(&format!("^(?:{})+$", syllable(&tactics.shape, cat, norm)))?)
+        };
+        let forbid = tactics.forbid.iter().map(|x| {
+            let mut pat = expand(x, cat, norm);
+            if let Some(rest) = pat.strip_prefix('#') {
+                pat = format!("^{}", rest);
+            }
+            if let Some(rest) = pat.strip_suffix('#') {
+                pat = format!("{}$", rest);
+            }
+            Regex::new(&pat).map(|re| (x.clone(), re))
+        }).collect::<Result<_, _>>()?;
+        Ok(Compiled {
+            vowel,
+            shape,
+            onset: anchored(&tactics.onset, cat, norm)?,
+            coda: anchored(&tactics.coda, cat, norm)?,
+            cluster: anchored(&tactics.cluster, cat, norm)?,
+            forbid,
+        })
+    }
+
+    // An empty list allows anything; nothing at all is always allowed.
+    fn allows(list: &[Regex], text: &str) -> bool {
+        text.is_empty() || list.is_empty() || list.iter().any(|x| x.is_match(text))
+    }
+
+    // Without a list of its own, a medial cluster has to split into an
+    // allowed coda followed by an allowed onset.
+    fn allows_medial(&self, text: &str) -> bool {
+        if !self.cluster.is_empty() {
+            return Compiled::allows(&self.cluster, text);
+        }
+        text.char_indices().map(|(i, _)| i).chain([text.len()]).any(|i| {
+            Compiled::allows(&self.coda, &text[..i]) && Compiled::allows(&self.onset, &text[i..])
+        })
+    }
+
+    fn check(&self, mnemonic: &str) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for word in mnemonic.split_whitespace() {
+            if self.shape.as_ref().is_some_and(|x| !x.is_match(word)) {
+                violations.push(Violation::Shape);
+            }
+            if let Some(vowel) = &self.vowel {
+                let nuclei: Vec<_> = vowel.find_iter(word).collect();
+                if let (Some(first), Some(last)) = (nuclei.first(), nuclei.last()) {
+                    let onset = &word[..first.start()];
+                    if !Compiled::allows(&self.onset, onset) {
+                        violations.push(Violation::Onset(onset.to_owned()));
+                    }
+                    let coda = &word[last.end()..];
+                    if !Compiled::allows(&self.coda, coda) {
+                        violations.push(Violation::Coda(coda.to_owned()));
+                    }
+                    for pair in nuclei.windows(2) {
+                        let medial = &word[pair[0].end()..pair[1].start()];
+                        if !medial.is_empty() && !self.allows_medial(medial) {
+                            violations.push(Violation::Cluster(medial.to_owned()));
+                        }
+                    }
+                }
+            }
+            for (name, re) in self.forbid.iter() {
+                if re.is_match(word) {
+                    violations.push(Violation::Forbidden(name.clone()));
+                }
+            }
+        }
+        violations
+    }
+}
+
+impl Language {
+    pub fn check_tactics(&self, tactics: &Phonotactics) -> Result<(), Box<dyn Error>> {
+        let cat = self.mnemonic_transform().normalized_cat()?;
+        Compiled::new(tactics, &cat, self.norm())?;
+        Ok(())
+    }
+
+    // Compiles the constraints once for checking many forms. Category edits
+    // can break constraints that compiled when they were set.
+    pub(super) fn validator(&self) -> Result<impl Fn(&str) -> Vec<Violation>, BabelError> {
+        let cat = self.mnemonic_transform().normalized_cat()?;
+        let compiled = Compiled::new(self.phonotactics(), &cat, self.norm()).map_err(|x| BabelError::BrokenRule(x.to_string()))?;
+        let norm = self.norm();
+        Ok(move |mnemonic: &str| compiled.check(&norm.apply(mnemonic)))
+    }
+
+    /// Every constraint `mnemonic` breaks.
+    pub fn validate(&self, mnemonic: &str) -> Result<Vec<Violation>, BabelError> {
+        Ok(self.validator()?(mnemonic))
+    }
+
+    /// Words in the lexicon that break a constraint, with what they break.
+    pub fn validate_all(&self) -> Result<Vec<(usize, Vec<Violation>)>, BabelError> {
+        let validate = self.validator()?;
+        Ok(self.enum_word()
+            .map(|(i, x)| (i, validate(x.mnemonic())))
+            .filter(|(_, x)| !x.is_empty())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::word::Word;
+
+    #[test]
+    fn test_phonotactics() {
+        let mut lang = Language::new("Test");
+        lang.add_cat('C', "ptkslr").unwrap();
+        lang.add_cat('V', "aiu").unwrap();
+        lang.set_phonotactics(
+            Phonotactics::new('V', "(C)(C)V(C)")
+                .with_onset(vec![String::from("C"), String::from("[ptk][lr]")])
+                .with_coda(vec![String::from("[sr]")])
+                .with_forbid(vec![String::from("ii"), String::from("#u")])
+        );
+        assert!(lang.validate("tlapa").unwrap().is_empty());
+        assert!(lang.validate("pastla").unwrap().is_empty());
+        assert_eq!(lang.validate("lta").unwrap(), vec![Violation::Onset(String::from("lt"))]);
+        assert_eq!(lang.validate("pak").unwrap(), vec![Violation::Coda(String::from("k"))]);
+        assert_eq!(lang.validate("pakta").unwrap(), vec![Violation::Cluster(String::from("kt"))]);
+        assert_eq!(lang.validate("stra").unwrap(), vec![Violation::Shape, Violation::Onset(String::from("str"))]);
+        assert_eq!(lang.validate("upii").unwrap(), vec![Violation::Forbidden(String::from("ii")), Violation::Forbidden(String::from("#u"))]);
+        lang.add_word(Word::shell("pak", 0)).unwrap();
+        assert_eq!(lang.validate_all().unwrap().len(), 1);
+        lang.add_cat('N', "q\u{303}t").unwrap();
+        lang.set_phonotactics(Phonotactics::new('V', "(N)V"));
+        assert!(lang.validate("q\u{303}a").unwrap().is_empty());
+        assert!(lang.validate("a").unwrap().is_empty());
+        lang.set_phonotactics(Phonotactics::new('V', "V").with_forbid(vec![String::from("(")]));
+        assert!(lang.validate("a").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use super::BabelError;
+use super::language::Language;
+use super::word::Word;
+
+const EM: f64 = 1000.0;
+const LINE: f64 = 1200.0;
+const SPACE: f64 = 500.0;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Glyph {
+    unit: String,
+    name: String,
+    #[serde(default)]
+    code: Option<char>,
+    #[serde(default)]
+    path: String,
+    #[serde(default = "Glyph::default_advance")]
+    advance: f64,
+}
+
+impl Glyph {
+    pub fn new(unit: &str, name: &str) -> Glyph {
+        Glyph {
+            unit: unit.to_owned(),
+            name: name.to_owned(),
+            code: None,
+            path: String::new(),
+            advance: Glyph::default_advance(),
+        }
+    }
+
+    fn default_advance() -> f64 {
+        EM
+    }
+
+    pub fn with_code(mut self, code: Option<char>) -> Glyph {
+        self.code = code;
+        self
+    }
+
+    /// `path` is SVG path data in a 1000-unit em box, y pointing down.
+    pub fn with_path(mut self, path: &str, advance: f64) -> Glyph {
+        self.path = path.to_owned();
+        self.advance = advance;
+        self
+    }
+
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn code(&self) -> Option<char> {
+        self.code
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn advance(&self) -> f64 {
+        self.advance
+    }
+
+    // Named glyphs without a code point are written like orthography
+    // combinations so they still survive as plain text.
+    fn text(&self) -> String {
+        match self.code {
+            Some(code) => code.to_string(),
+            None => format!("{{{}}}", self.name),
+        }
+    }
+}
+
+enum Piece<'a> {
+    Glyph(&'a Glyph),
+    Other(char),
+}
+
+impl Piece<'_> {
+    fn text(&self) -> String {
+        match self {
+            Piece::Glyph(glyph) => glyph.text(),
+            Piece::Other(x) => x.to_string(),
+        }
+    }
+}
+
+fn xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl Language {
+    // Longest unit first, so syllable or morpheme glyphs beat the letters
+    // they are made of.
+    fn transcribe(&self, written: &str) -> Vec<Piece<'_>> {
+        let mut pieces = Vec::new();
+        let mut rest = written;
+        while let Some(x) = rest.chars().next() {
+            let found = self.enum_glyph()
+                .filter(|(_, g)| !g.unit.is_empty() && rest.starts_with(&g.unit))
+                .max_by_key(|(_, g)| g.unit.len());
+            match found {
+                Some((_, glyph)) => {
+                    pieces.push(Piece::Glyph(glyph));
+                    rest = &rest[glyph.unit.len()..];
+                }
+                None => {
+                    pieces.push(Piece::Other(x));
+                    rest = &rest[x.len_utf8()..];
+                }
+            }
+        }
+        pieces
+    }
+
+    /// Writes a spelt form in the language's script.
+    pub fn script(&self, written: &str) -> String {
+        self.transcribe(written).into_iter().map(|x| x.text()).collect()
+    }
+
+    /// Draws lines of mnemonic text, spelt through `mnemonic_to_word`, as an
+    /// SVG image built from the glyphs' paths.
+    pub fn export_svg(&self, lines: &[&str]) -> Result<String, BabelError> {
+        let m2w = self.make_m2w()?;
+        let mut body = String::new();
+        let mut width: f64 = 0.0;
+        for (i, line) in lines.iter().enumerate() {
+            let y = i as f64 * LINE;
+            let mut x = 0.0;
+            for (j, mnemonic) in line.split_whitespace().enumerate() {
+                if j > 0 {
+                    x += SPACE;
+                }
+                for piece in self.transcribe(&Word::render(mnemonic, &m2w)) {
+                    match piece {
+                        Piece::Glyph(glyph) if !glyph.path.is_empty() => {
+                            writeln!(
+                                body,
+                                "  <path transform=\"translate({} {})\" d=\"{}\"><title>{}</title></path>",
+                                x, y, xml(&glyph.path), xml(&glyph.name)
+                            ).unwrap();
+                            x += glyph.advance;
+                        }
+                        // Glyphs without an outline are left to a font.
+                        piece => {
+                            writeln!(
+                                body,
+                                "  <text x=\"{}\" y=\"{}\" font-size=\"{}\">{}</text>",
+                                x, y + EM * 0.8, EM, xml(&piece.text())
+                            ).unwrap();
+                            x += match piece {
+                                Piece::Glyph(glyph) => glyph.advance,
+                                Piece::Other(_) => EM * 0.6,
+                            };
+                        }
+                    }
+                }
+            }
+            width = width.max(x);
+        }
+        let height = lines.len() as f64 * LINE;
+        Ok(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\">\n{}</svg>\n",
+            width.max(EM), height.max(LINE), width.max(EM) / 20.0, height.max(LINE) / 20.0, body
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let mut lang = Language::new("Test");
+        lang.add_glyph(Glyph::new("k", "ka").with_code(Some('\u{e000}')));
+        lang.add_glyph(Glyph::new("ki", "ki").with_code(Some('\u{e001}')));
+        lang.add_glyph(Glyph::new("a", "a").with_path("M0 0L500 1000", 500.0));
+        assert_eq!(lang.script("kika!"), "\u{e001}\u{e000}{a}!");
+        let svg = lang.export_svg(&["ka"]).unwrap();
+        assert!(svg.contains("d=\"M0 0L500 1000\""));
+        assert!(svg.contains("translate(1000 0)"));
+        assert!(svg.contains(">\u{e000}</text>"));
+        lang.add_glyph(Glyph::new("1", "one"));
+        assert_eq!(lang.glyph_idx("1").unwrap(), 3);
+        assert_eq!(lang.glyph_idx("one").unwrap(), 3);
+        assert_eq!(lang.glyph_idx("2").unwrap(), 2);
+        assert!(lang.glyph_idx("9").is_err());
+    }
+}
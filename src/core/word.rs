@@ -1,7 +1,11 @@
-use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
 // use serde_json::Result as JsonResult;
 use regex::Regex;
 use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use super::BabelError;
 use super::language::Substitute;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
@@ -24,41 +28,316 @@ impl Coordinate {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Word {
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Variant {
+    conlang: String,
+    upa: String,
+    mnemonic: String,
+}
+
+impl Variant {
+    pub fn conlang(&self) -> &str {
+        &self.conlang
+    }
+
+    pub fn upa(&self) -> &str {
+        &self.upa
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+}
+
+/// How a sense relates to the senses of the word it descends from.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Shift {
+    #[default]
+    Gained,
+    Inherited,
+    Shifted,
+    Lost,
+}
+
+impl FromStr for Shift {
+    type Err = BabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gained" => Ok(Shift::Gained),
+            "inherited" => Ok(Shift::Inherited),
+            "shifted" => Ok(Shift::Shifted),
+            "lost" => Ok(Shift::Lost),
+            _ => Err(BabelError::UnknownLabel(s.to_owned())),
+        }
+    }
+}
+
+impl Display for Shift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Shift::Gained => write!(f, "gained"),
+            Shift::Inherited => write!(f, "inherited"),
+            Shift::Shifted => write!(f, "shifted"),
+            Shift::Lost => write!(f, "lost"),
+        }
+    }
+}
+
+/// Glosses keyed by natural-language code. A gloss saved as plain text
+/// belongs to the empty code, which stands for "not marked".
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Gloss(IndexMap<String, String>);
+
+impl<'de> Deserialize<'de> for Gloss {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Text(String),
+            Map(IndexMap<String, String>),
+        }
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Text(text) => Gloss::from(text.as_str()),
+            Stored::Map(map) => Gloss(map),
+        })
+    }
+}
+
+impl From<&str> for Gloss {
+    fn from(text: &str) -> Self {
+        let mut gloss = Gloss::default();
+        gloss.set("", text);
+        gloss
+    }
+}
+
+impl Gloss {
+    pub fn text(&self, code: &str) -> Option<&str> {
+        self.0.get(code).map(|x| x.as_str())
+    }
+
+    /// Sets the gloss for `code`; empty text drops it.
+    pub fn set(&mut self, code: &str, text: &str) {
+        if text.is_empty() {
+            self.0.shift_remove(code);
+        } else {
+            self.0.insert(code.to_owned(), text.to_owned());
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(code, text)| (code.as_str(), text.as_str()))
+    }
+
+    /// Reads an unmarked "cat / Katze" gloss as one gloss per code, in order.
+    /// `None` if the parts don't line up with the codes.
+    pub fn split(&self, codes: &[String]) -> Option<Gloss> {
+        if self.0.len() != 1 {
+            return None;
+        }
+        let parts: Vec<&str> = self.text("")?.split('/').map(|x| x.trim()).collect();
+        if parts.len() != codes.len() || parts.iter().any(|x| x.is_empty()) {
+            return None;
+        }
+        let mut gloss = Gloss::default();
+        for (code, part) in codes.iter().zip(parts) {
+            gloss.set(code, part);
+        }
+        Some(gloss)
+    }
+
+    /// The glosses in `codes`, in that order; every gloss if `codes` is empty
+    /// or none of them is there.
+    pub fn show(&self, codes: &[String]) -> String {
+        let chosen: Vec<&str> = codes.iter().filter_map(|x| self.text(x)).collect();
+        if chosen.is_empty() {
+            self.0.values().map(|x| x.as_str()).collect::<Vec<_>>().join(" / ")
+        } else {
+            chosen.join(" / ")
+        }
+    }
+
+    pub fn contains(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.0.values().any(|x| x.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Sense {
+    gloss: Gloss,
+    #[serde(default)]
+    definition: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    register: String,
+    #[serde(default)]
+    example: String,
+    #[serde(default)]
+    shift: Shift,
+    // Index of the ancestor's sense this one descends from.
+    #[serde(default)]
+    origin: Option<usize>,
+}
+
+impl Sense {
+    pub fn new(gloss: Gloss) -> Sense {
+        Sense { gloss, ..Sense::default() }
+    }
+
+    pub fn with_definition(mut self, definition: &str) -> Sense {
+        self.definition = definition.to_owned();
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Sense {
+        self.note = note.to_owned();
+        self
+    }
+
+    pub fn with_register(mut self, register: &str) -> Sense {
+        self.register = register.to_owned();
+        self
+    }
+
+    pub fn with_example(mut self, example: &str) -> Sense {
+        self.example = example.to_owned();
+        self
+    }
+
+    pub fn with_shift(mut self, shift: Shift) -> Sense {
+        self.shift = shift;
+        self
+    }
+
+    pub fn gloss(&self) -> &Gloss {
+        &self.gloss
+    }
+
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    /// Usage note.
+    pub fn note(&self) -> &str {
+        &self.note
+    }
+
+    pub fn register(&self) -> &str {
+        &self.register
+    }
+
+    pub fn example(&self) -> &str {
+        &self.example
+    }
+
+    pub fn shift(&self) -> Shift {
+        self.shift
+    }
+}
+
+// Words saved before senses existed carry a single `natlang` gloss and a
+// free-form `info`, which become the first sense and its usage note.
+#[derive(Deserialize)]
+struct StoredWord {
     conlang: String,
+    #[serde(default)]
     natlang: String,
     pos: usize,
     upa: String,
     mnemonic: String,
     ancestor: Vec<Coordinate>,
+    #[serde(default)]
     info: String,
+    #[serde(default)]
+    sense: Vec<Sense>,
+    #[serde(default)]
+    variant: Vec<Variant>,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    inflection: IndexMap<String, String>,
+    #[serde(default)]
+    derived: IndexMap<String, Vec<String>>,
+    #[serde(default)]
+    feature: IndexMap<String, String>,
+}
+
+impl From<StoredWord> for Word {
+    fn from(stored: StoredWord) -> Self {
+        let mut sense = stored.sense;
+        if sense.is_empty() && !(stored.natlang.is_empty() && stored.info.is_empty()) {
+            sense.push(Sense::new(Gloss::from(stored.natlang.as_str())).with_note(&stored.info));
+        }
+        Word {
+            conlang: stored.conlang,
+            pos: stored.pos,
+            upa: stored.upa,
+            mnemonic: stored.mnemonic,
+            ancestor: stored.ancestor,
+            sense,
+            variant: stored.variant,
+            class: stored.class,
+            inflection: stored.inflection,
+            derived: stored.derived,
+            feature: stored.feature,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(from = "StoredWord")]
+pub struct Word {
+    conlang: String,
+    pos: usize,
+    upa: String,
+    mnemonic: String,
+    ancestor: Vec<Coordinate>,
+    sense: Vec<Sense>,
+    variant: Vec<Variant>,
+    class: String,
+    inflection: IndexMap<String, String>,
+    derived: IndexMap<String, Vec<String>>,
+    feature: IndexMap<String, String>,
 }
 
 impl Word {
-    pub fn shell(
-        mnemonic: &str,
-        natlang: &str,
-        pos: usize,
-        info: &str
-    ) -> Word {
+    pub fn shell(mnemonic: &str, pos: usize) -> Word {
         Word {
             conlang: String::new(),
-            natlang: natlang.to_string(),
             pos,
             upa: String::new(),
             mnemonic: mnemonic.to_string(),
             ancestor: Vec::new(),
-            info: info.to_string()
+            sense: Vec::new(),
+            variant: Vec::new(),
+            class: String::new(),
+            inflection: IndexMap::new(),
+            derived: IndexMap::new(),
+            feature: IndexMap::new(),
         }
     }
 
+    pub fn with_sense(mut self, sense: Vec<Sense>) -> Word {
+        self.sense = sense;
+        self
+    }
+
     pub fn conlang(&self) -> &str {
         &self.conlang
     }
 
-    pub fn natlang(&self) -> &str {
-        &self.natlang
+    /// The gloss of the first sense the word hasn't lost.
+    pub fn gloss(&self) -> Option<&Gloss> {
+        self.sense.iter().find(|x| x.shift != Shift::Lost).map(|x| &x.gloss)
+    }
+
+    /// Whether any gloss of any sense, in any language, contains `query`.
+    pub fn glossed(&self, query: &str) -> bool {
+        self.sense.iter().any(|x| x.gloss.contains(query))
     }
 
     pub fn pos(&self) -> usize {
@@ -77,16 +356,121 @@ impl Word {
         &self.ancestor
     }
 
-    pub fn add_ancestor(&mut self, coord: Coordinate) {
-        self.ancestor.push(coord);
-    }
-
     pub fn set_ancestor(&mut self, other: &[Coordinate]) {
         self.ancestor = other.to_vec();
     }
 
-    pub fn info(&self) -> &str {
-        &self.info
+    pub fn sense(&self) -> &Vec<Sense> {
+        &self.sense
+    }
+
+    pub fn sense_at(&self, idx: usize) -> Result<&Sense, BabelError> {
+        self.sense.get(idx).ok_or(BabelError::IndexOutOfRange)
+    }
+
+    // Returns how many slashed glosses were split and how many didn't fit.
+    pub(super) fn split_gloss(&mut self, codes: &[String]) -> (usize, usize) {
+        let mut count = (0, 0);
+        for sense in self.sense.iter_mut().filter(|x| x.gloss.text("").is_some_and(|x| x.contains('/'))) {
+            match sense.gloss.split(codes) {
+                Some(gloss) => {
+                    sense.gloss = gloss;
+                    count.0 += 1;
+                }
+                None => count.1 += 1,
+            }
+        }
+        count
+    }
+
+    pub fn add_sense(&mut self, sense: Sense) {
+        self.sense.push(sense);
+    }
+
+    pub fn alt_sense(&mut self, idx: usize, sense: Sense) -> Result<(), BabelError> {
+        let old = self.sense.get_mut(idx).ok_or(BabelError::IndexOutOfRange)?;
+        *old = Sense { origin: old.origin, ..sense };
+        Ok(())
+    }
+
+    pub fn rm_sense(&mut self, idx: usize) -> Result<(), BabelError> {
+        if idx >= self.sense.len() {
+            return Err(BabelError::IndexOutOfRange);
+        }
+        self.sense.remove(idx);
+        Ok(())
+    }
+
+    // What a daughter word starts out meaning: everything its ancestor
+    // still means.
+    fn inherited_sense(&self) -> impl Iterator<Item = Sense> + '_ {
+        self.sense.iter().enumerate().filter(|(_, x)| x.shift != Shift::Lost).map(|(i, x)| Sense {
+            shift: Shift::Inherited,
+            origin: Some(i),
+            ..x.clone()
+        })
+    }
+
+    pub fn variant(&self) -> &Vec<Variant> {
+        &self.variant
+    }
+
+    /// Name of the inflection class the word follows; empty for the default
+    /// class of its part of speech.
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    pub fn set_class(&mut self, class: &str) {
+        self.class = class.to_owned();
+    }
+
+    /// Irregular forms set by hand, as mnemonics keyed by paradigm cell.
+    pub fn inflection(&self) -> &IndexMap<String, String> {
+        &self.inflection
+    }
+
+    /// Overrides a paradigm cell; an empty mnemonic makes it regular again.
+    pub fn set_inflection(&mut self, cell: &str, mnemonic: &str) {
+        if mnemonic.is_empty() {
+            self.inflection.shift_remove(cell);
+        } else {
+            self.inflection.insert(cell.to_owned(), mnemonic.to_owned());
+        }
+    }
+
+    /// Forms the last derivation gave each cell, every variant kept. They
+    /// are replaced on each derivation; `inflection` wins over them.
+    pub fn derived(&self) -> &IndexMap<String, Vec<String>> {
+        &self.derived
+    }
+
+    /// An empty list drops the cell.
+    pub fn set_derived(&mut self, cell: &str, forms: Vec<String>) {
+        if forms.is_empty() {
+            self.derived.shift_remove(cell);
+        } else {
+            self.derived.insert(cell.to_owned(), forms);
+        }
+    }
+
+    /// Values of the word's grammatical categories, keyed by category name.
+    pub fn feature(&self) -> &IndexMap<String, String> {
+        &self.feature
+    }
+
+    /// Sets a category's value; an empty value leaves it unset.
+    pub fn set_feature(&mut self, category: &str, value: &str) {
+        if value.is_empty() {
+            self.feature.shift_remove(category);
+        } else {
+            self.feature.insert(category.to_owned(), value.to_owned());
+        }
+    }
+
+    /// Whether every category in `feature` has the given value.
+    pub fn matches(&self, feature: &IndexMap<String, String>) -> bool {
+        feature.iter().all(|(k, v)| self.feature.get(k) == Some(v))
     }
 
     fn replace_all(re: &Regex, text: &str, rep: &str) -> String {
@@ -99,38 +483,95 @@ impl Word {
         }
     }
 
+    /// Takes the forms of a freshly derived `other`, keeping this word's own
+    /// senses and picking up any ancestral sense it has no record of yet.
     pub fn fuse(&mut self, other: Word) {
         self.conlang = other.conlang;
         self.upa = other.upa;
         self.mnemonic = other.mnemonic;
+        self.variant = other.variant;
+        self.derived = other.derived;
+        // Senses derived before origins were kept fall back to their gloss.
+        for sense in other.sense {
+            let found = self.sense.iter().position(|x| x.origin == sense.origin)
+                .or_else(|| self.sense.iter().position(|x| x.origin.is_none() && x.gloss == sense.gloss));
+            match found {
+                Some(i) if self.sense[i].shift == Shift::Inherited => self.sense[i] = sense,
+                Some(i) => self.sense[i].origin = sense.origin,
+                None => self.sense.push(sense),
+            }
+        }
     }
 
-    pub fn morph(&mut self, m2w: &Vec<Substitute>, m2u: &Vec<Substitute>) {
-        let mut conlang = self.mnemonic.to_owned();
-        for sub in m2w {
-            // conlang = Word::replace_all(sub.pat(), &conlang, sub.repl());
-            if let Cow::Owned(s) = sub.pat().replace_all(&conlang, sub.repl()) {
-                conlang = s;
+    pub(super) fn render(mnemonic: &str, subs: &[Substitute]) -> String {
+        let mut text = mnemonic.to_owned();
+        for sub in subs {
+            // text = Word::replace_all(sub.pat(), &text, sub.repl());
+            if let Cow::Owned(s) = sub.pat().replace_all(&text, sub.repl()) {
+                text = s;
             }
         }
-        self.conlang = conlang;
-        let mut upa = self.mnemonic.to_owned();
-        for sub in m2u {
-            // upa = Word::replace_all(sub.pat(), &upa, sub.repl());
-            if let Cow::Owned(s) = sub.pat().replace_all(&upa, sub.repl()) {
-                upa = s;
-            }
+        text
+    }
+
+    pub fn spell(&self, subs: &[Substitute]) -> String {
+        Word::render(&self.mnemonic, subs)
+    }
+
+    pub fn morph(&mut self, m2w: &[Substitute], m2u: &[Substitute]) {
+        self.conlang = Word::render(&self.mnemonic, m2w);
+        self.upa = Word::render(&self.mnemonic, m2u);
+        for variant in self.variant.iter_mut() {
+            variant.conlang = Word::render(&variant.mnemonic, m2w);
+            variant.upa = Word::render(&variant.mnemonic, m2u);
         }
-        self.upa = upa;
     }
 
-    pub fn labor(&self, coord: Coordinate, mnt: &Vec<Substitute>, m2w: &Vec<Substitute>, m2u: &Vec<Substitute>) -> Word {
-        let mut mnemonic = self.mnemonic.to_owned();
+    /// Every outcome of running `mnemonic` through the sound changes, the
+    /// one with no optional change skipped first. An optional change applies
+    /// to all of its matches in a form or to none of them.
+    pub(super) fn evolve(mnemonic: &str, mnt: &[Substitute]) -> Vec<String> {
+        let mut forms = vec![mnemonic.to_owned()];
         for sub in mnt {
-            mnemonic = Word::replace_all(sub.pat(), &mnemonic, sub.repl());
+            let mut next: Vec<String> = Vec::new();
+            for form in forms {
+                let changed = Word::replace_all(sub.pat(), &form, sub.repl());
+                let candidates = if sub.optional() && changed != form {
+                    vec![changed, form]
+                } else {
+                    vec![changed]
+                };
+                for x in candidates {
+                    if !next.contains(&x) {
+                        next.push(x);
+                    }
+                }
+            }
+            forms = next;
+        }
+        forms
+    }
+
+    pub fn labor(&self, coord: Coordinate, mnt: &[Substitute], m2w: &[Substitute], m2u: &[Substitute]) -> Word {
+        let mut forms = Word::evolve(&self.mnemonic, mnt);
+        for variant in self.variant.iter() {
+            for form in Word::evolve(&variant.mnemonic, mnt) {
+                if !forms.contains(&form) {
+                    forms.push(form);
+                }
+            }
         }
-        let mut word = Self::shell(&mnemonic, &self.natlang, self.pos, &self.info);
+        let mut forms = forms.into_iter();
+        let mnemonic = forms.next().unwrap_or_default();
+        let mut word = Self::shell(&mnemonic, self.pos).with_sense(self.inherited_sense().collect());
+        word.class = self.class.clone();
+        word.feature = self.feature.clone();
         word.ancestor.push(coord);
+        word.variant = forms.map(|mnemonic| Variant {
+            conlang: String::new(),
+            upa: String::new(),
+            mnemonic,
+        }).collect();
         word.morph(m2w, m2u);
         word
     }
@@ -182,4 +623,70 @@ impl Word {
 //     fn is_alive(&self) -> bool {
 //         !self.conlang.is_empty()
 //     }
-// }
\ No newline at end of file
+// }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_optional_variant() {
+        let mnt = vec![
+            Substitute::new("t", "d").unwrap().with_optional(true),
+            Substitute::new("a$", "e").unwrap(),
+        ];
+        let word = Word::shell("kata", 0);
+        let neo_word = word.labor(Coordinate::new(0, 0), &mnt, &[], &[]);
+        assert_eq!(neo_word.mnemonic(), "kade");
+        assert_eq!(neo_word.variant().len(), 1);
+        assert_eq!(neo_word.variant()[0].conlang(), "kate");
+        let mnt = vec![Substitute::new("e$", "i").unwrap()];
+        let neo_word = neo_word.labor(Coordinate::new(1, 0), &mnt, &[], &[]);
+        assert_eq!(neo_word.mnemonic(), "kadi");
+        assert_eq!(neo_word.variant()[0].mnemonic(), "kati");
+    }
+
+    #[test]
+    fn test_sense() {
+        let old: Word = serde_json::from_str(
+            r#"{"conlang":"","natlang":"cat","pos":0,"upa":"","mnemonic":"kata","ancestor":[],"info":"feline"}"#
+        ).unwrap();
+        assert_eq!((old.gloss(), old.sense()[0].note()), (Some(&Gloss::from("cat")), "feline"));
+        let mut word = Word::shell("kata", 0).with_sense(vec![
+            Sense::new("cat".into()),
+            Sense::new("tiger".into()).with_shift(Shift::Lost),
+        ]);
+        let mut neo_word = word.labor(Coordinate::new(0, 0), &[], &[], &[]);
+        assert_eq!(neo_word.sense().len(), 1);
+        assert_eq!(neo_word.sense()[0].shift(), Shift::Inherited);
+        neo_word.alt_sense(0, Sense::new("cat".into()).with_shift(Shift::Lost)).unwrap();
+        neo_word.add_sense(Sense::new("lion".into()));
+        assert_eq!(neo_word.gloss(), Some(&Gloss::from("lion")));
+        neo_word.fuse(word.labor(Coordinate::new(0, 0), &[], &[], &[]));
+        assert_eq!(neo_word.sense().len(), 2);
+        let mut kept = word.labor(Coordinate::new(0, 0), &[], &[], &[]);
+        neo_word.alt_sense(0, Sense::new("dog".into()).with_shift(Shift::Shifted)).unwrap();
+        let mut gloss = Gloss::from("cat");
+        gloss.set("de", "Katze");
+        word.alt_sense(0, Sense::new(gloss)).unwrap();
+        neo_word.fuse(word.labor(Coordinate::new(0, 0), &[], &[], &[]));
+        assert_eq!(neo_word.sense().len(), 2);
+        kept.fuse(word.labor(Coordinate::new(0, 0), &[], &[], &[]));
+        assert_eq!(kept.sense().len(), 1);
+        assert_eq!(kept.sense()[0].gloss().text("de"), Some("Katze"));
+    }
+
+    #[test]
+    fn test_gloss() {
+        let gloss: Gloss = serde_json::from_str(r#"{"en":"cat","zh":"猫"}"#).unwrap();
+        assert_eq!(gloss.show(&[String::from("zh"), String::from("en")]), "猫 / cat");
+        assert_eq!(gloss.show(&[String::from("fr")]), "cat / 猫");
+        assert!(gloss.contains("CAT") && gloss.contains("猫"));
+        let gloss: Gloss = serde_json::from_str(r#""cat""#).unwrap();
+        assert_eq!(gloss.text(""), Some("cat"));
+        let codes = [String::from("en"), String::from("de")];
+        let mut word = Word::shell("kata", 0).with_sense(vec![Sense::new("cat / Katze".into()), Sense::new("a/b/c".into())]);
+        assert_eq!(word.split_gloss(&codes), (1, 1));
+        assert_eq!(word.sense()[0].gloss().show(&codes), "cat / Katze");
+        assert_eq!(word.sense()[0].gloss().text("de"), Some("Katze"));
+    }
+}